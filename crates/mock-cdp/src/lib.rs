@@ -0,0 +1,495 @@
+//! In-process mock of Chrome's DevTools WebSocket endpoint, for integration tests
+//!
+//! `MockCdpServer::start` listens on a random local port and models just enough
+//! of a browser to drive a client through the usual flow: targets can be
+//! listed, created, attached to (flattened sessions) and closed, pages can be
+//! navigated and scripts evaluated. Any method can be overridden with `on`,
+//! errors and disconnects can be injected, and events can be pushed at any time
+//! with `emit`.
+//!
+//! With the `tls` feature, `MockCdpServer::start_tls` serves `wss://` instead,
+//! using a certificate for `localhost`/`127.0.0.1` signed by `TEST_CA_BUNDLE`.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use mock_cdp::{MockCdpServer, Reply};
+//! use serde_json::json;
+//!
+//! let server = MockCdpServer::start().await?;
+//! server.on("Runtime.evaluate", |_| Reply::result(json!({ "result": { "type": "number", "value": 42 } })));
+//! server.fail_next("Page.navigate", -32000, "net::ERR_NAME_NOT_RESOLVED");
+//! // connect the client under test to server.ws_url()
+//! # Ok(())
+//! # }
+//! ```
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Target id of the page that exists when the server starts
+pub const INITIAL_TARGET_ID: &str = "PAGE-1";
+
+/// Prefix of the session ids handed out by `Target.attachToTarget`, followed by the target id
+pub const SESSION_PREFIX: &str = "SESSION-";
+
+/// PEM file with the CA that signed the certificate served by `start_tls`
+#[cfg(feature = "tls")]
+pub const TEST_CA_BUNDLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/ca.pem");
+
+/// A command received from a client
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub id: i64,
+    pub method: String,
+    /// `{}` when the command carried no params
+    pub params: Value,
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Outcome {
+    Result(Value),
+    Error { code: i64, message: String },
+    Disconnect,
+    Silence,
+}
+
+/// What the server does in answer to a command
+#[derive(Debug, Clone)]
+pub struct Reply {
+    outcome: Outcome,
+    events: Vec<(String, Value)>,
+}
+
+impl Reply {
+    pub fn result(result: Value) -> Self {
+        Self { outcome: Outcome::Result(result), events: Vec::new() }
+    }
+
+    pub fn error(code: i64, message: impl Into<String>) -> Self {
+        Self { outcome: Outcome::Error { code, message: message.into() }, events: Vec::new() }
+    }
+
+    /// Drop the connection without answering or sending a close frame
+    pub fn disconnect() -> Self {
+        Self { outcome: Outcome::Disconnect, events: Vec::new() }
+    }
+
+    /// Never answer, e.g. to exercise client timeouts
+    pub fn silence() -> Self {
+        Self { outcome: Outcome::Silence, events: Vec::new() }
+    }
+
+    /// Send an event after the reply, in the same session as the command
+    pub fn then_emit(mut self, method: impl Into<String>, params: Value) -> Self {
+        self.events.push((method.into(), params));
+        self
+    }
+}
+
+type Handler = Arc<dyn Fn(&Request) -> Reply + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct Target {
+    id: String,
+    url: String,
+}
+
+impl Target {
+    fn info(&self) -> Value {
+        json!({
+            "targetId": self.id,
+            "type": "page",
+            "title": self.url,
+            "url": self.url,
+            "attached": false,
+            "canAccessOpener": false,
+        })
+    }
+}
+
+enum Push {
+    Frame(String),
+    Disconnect,
+}
+
+struct State {
+    handlers: HashMap<String, Handler>,
+    injected: HashMap<String, VecDeque<Reply>>,
+    targets: Vec<Target>,
+    next_target: u64,
+    next_loader: u64,
+    received: Vec<Request>,
+    clients: Vec<mpsc::UnboundedSender<Push>>,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            injected: HashMap::new(),
+            targets: vec![Target { id: INITIAL_TARGET_ID.to_string(), url: "about:blank".to_string() }],
+            next_target: 2,
+            next_loader: 1,
+            received: Vec::new(),
+            clients: Vec::new(),
+        }
+    }
+
+    fn target_for_session(&mut self, session_id: Option<&str>) -> Option<&mut Target> {
+        let target_id = session_id?.strip_prefix(SESSION_PREFIX)?;
+        self.targets.iter_mut().find(|target| target.id == target_id)
+    }
+
+    /// The built-in browser model, used when no handler or injected reply applies
+    fn respond(&mut self, request: &Request) -> Reply {
+        let param = |name: &str| request.params.get(name).and_then(Value::as_str).unwrap_or_default().to_string();
+
+        match request.method.as_str() {
+            "Browser.getVersion" => Reply::result(json!({
+                "protocolVersion": "1.3",
+                "product": "MockChrome/1.0",
+                "revision": "@0",
+                "userAgent": "MockChrome/1.0",
+                "jsVersion": "0.0",
+            })),
+            "Target.getTargets" => {
+                Reply::result(json!({ "targetInfos": self.targets.iter().map(Target::info).collect::<Vec<_>>() }))
+            }
+            "Target.createTarget" => {
+                let target = Target { id: format!("PAGE-{}", self.next_target), url: param("url") };
+                self.next_target += 1;
+                self.targets.push(target.clone());
+                Reply::result(json!({ "targetId": target.id }))
+                    .then_emit("Target.targetCreated", json!({ "targetInfo": target.info() }))
+            }
+            "Target.attachToTarget" => match self.targets.iter().find(|target| target.id == param("targetId")) {
+                Some(target) => {
+                    let session_id = format!("{}{}", SESSION_PREFIX, target.id);
+                    Reply::result(json!({ "sessionId": session_id })).then_emit(
+                        "Target.attachedToTarget",
+                        json!({ "sessionId": session_id, "targetInfo": target.info(), "waitingForDebugger": false }),
+                    )
+                }
+                None => Reply::error(-32602, "No target with given id found"),
+            },
+            "Target.closeTarget" => {
+                let target_id = param("targetId");
+                match self.targets.iter().position(|target| target.id == target_id) {
+                    Some(index) => {
+                        self.targets.remove(index);
+                        Reply::result(json!({ "success": true }))
+                            .then_emit("Target.targetDestroyed", json!({ "targetId": target_id }))
+                    }
+                    None => Reply::error(-32602, "No target with given id found"),
+                }
+            }
+            "Page.navigate" => {
+                let loader_id = format!("LOADER-{}", self.next_loader);
+                self.next_loader += 1;
+                match self.target_for_session(request.session_id.as_deref()) {
+                    Some(target) => {
+                        target.url = param("url");
+                        Reply::result(json!({ "frameId": target.id, "loaderId": loader_id }))
+                            .then_emit("Page.frameNavigated", json!({ "frame": { "id": target.id, "loaderId": loader_id, "url": target.url } }))
+                            .then_emit("Page.loadEventFired", json!({ "timestamp": 0.0 }))
+                    }
+                    // Like Chrome, the browser target has no Page domain
+                    None => Reply::error(-32601, "'Page.navigate' wasn't found"),
+                }
+            }
+            "Runtime.evaluate" => Reply::result(json!({ "result": evaluate(&param("expression")) })),
+            method if method.starts_with("Target.") || method.ends_with(".enable") || method.ends_with(".disable") => {
+                Reply::result(json!({}))
+            }
+            method => Reply::error(-32601, format!("'{}' wasn't found", method)),
+        }
+    }
+}
+
+/// JSON literals evaluate to themselves; anything else to `undefined`
+fn evaluate(expression: &str) -> Value {
+    match serde_json::from_str::<Value>(expression) {
+        Ok(Value::Null) => json!({ "type": "object", "subtype": "null", "value": null }),
+        Ok(value @ Value::Bool(_)) => json!({ "type": "boolean", "value": value }),
+        Ok(value @ Value::Number(_)) => json!({ "type": "number", "value": value, "description": value.to_string() }),
+        Ok(value @ Value::String(_)) => json!({ "type": "string", "value": value }),
+        Ok(_) | Err(_) => json!({ "type": "undefined" }),
+    }
+}
+
+/// Mock browser endpoint. Stops accepting and drops every client when dropped.
+pub struct MockCdpServer {
+    addr: SocketAddr,
+    scheme: &'static str,
+    state: Arc<Mutex<State>>,
+    acceptor: JoinHandle<()>,
+}
+
+impl MockCdpServer {
+    /// Listen on a random port on 127.0.0.1
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::new()));
+        let acceptor = tokio::spawn(accept_loop(listener, state.clone()));
+        Ok(Self { addr, scheme: "ws", state, acceptor })
+    }
+
+    /// Like `start`, but clients must connect over TLS and trust `TEST_CA_BUNDLE`
+    #[cfg(feature = "tls")]
+    pub async fn start_tls() -> std::io::Result<Self> {
+        let identity = native_tls::Identity::from_pkcs8(
+            include_bytes!("../certs/server.pem"),
+            include_bytes!("../certs/server.key"),
+        )
+        .and_then(native_tls::TlsAcceptor::new)
+        .map_err(std::io::Error::other)?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::new()));
+        let acceptor = tokio::spawn(accept_tls_loop(listener, state.clone(), identity.into()));
+        Ok(Self { addr, scheme: "wss", state, acceptor })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Browser-level WebSocket URL to connect to, `wss://` for a server from `start_tls`
+    pub fn ws_url(&self) -> String {
+        format!("{}://{}/devtools/browser/mock", self.scheme, self.addr)
+    }
+
+    /// Answer `method` with `handler` instead of the built-in behaviour
+    pub fn on(&self, method: impl Into<String>, handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) {
+        self.state.lock().unwrap().handlers.insert(method.into(), Arc::new(handler));
+    }
+
+    /// Answer the next `method` command with a CDP error
+    pub fn fail_next(&self, method: impl Into<String>, code: i64, message: impl Into<String>) {
+        self.inject(method.into(), Reply::error(code, message));
+    }
+
+    /// Drop the connection when the next `method` command arrives
+    pub fn disconnect_on(&self, method: impl Into<String>) {
+        self.inject(method.into(), Reply::disconnect());
+    }
+
+    /// Send an event to every connected client
+    pub fn emit(&self, method: &str, params: Value, session_id: Option<&str>) {
+        let frame = event_frame(method, params, session_id);
+        self.state.lock().unwrap().clients.retain(|client| client.send(Push::Frame(frame.clone())).is_ok());
+    }
+
+    /// Drop every connected client without a close frame
+    pub fn disconnect_all(&self) {
+        for client in self.state.lock().unwrap().clients.drain(..) {
+            let _ = client.send(Push::Disconnect);
+        }
+    }
+
+    /// Clients currently connected
+    pub fn connection_count(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.clients.retain(|client| !client.is_closed());
+        state.clients.len()
+    }
+
+    /// Every command received so far, oldest first
+    pub fn received(&self) -> Vec<Request> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    fn inject(&self, method: String, reply: Reply) {
+        self.state.lock().unwrap().injected.entry(method).or_default().push_back(reply);
+    }
+}
+
+impl Drop for MockCdpServer {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+        self.disconnect_all();
+    }
+}
+
+async fn accept_loop(listener: TcpListener, state: Arc<Mutex<State>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(serve_client(stream, state.clone()));
+            }
+            Err(e) => log::warn!("Mock CDP server accept failed: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+async fn accept_tls_loop(listener: TcpListener, state: Arc<Mutex<State>>, tls: tokio_native_tls::TlsAcceptor) {
+    let tls = Arc::new(tls);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let (tls, state) = (tls.clone(), state.clone());
+                tokio::spawn(async move {
+                    match tls.accept(stream).await {
+                        Ok(stream) => serve_client(stream, state).await,
+                        Err(e) => log::debug!("Mock CDP TLS handshake failed: {}", e),
+                    }
+                });
+            }
+            Err(e) => log::warn!("Mock CDP server accept failed: {}", e),
+        }
+    }
+}
+
+async fn serve_client<S: AsyncRead + AsyncWrite + Unpin>(stream: S, state: Arc<Mutex<State>>) {
+    let socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::debug!("Mock CDP handshake failed: {}", e);
+            return;
+        }
+    };
+    let (mut write, mut read) = socket.split();
+    let (tx, mut pushes) = mpsc::unbounded_channel();
+    state.lock().unwrap().clients.push(tx);
+
+    // Returning early drops the socket without a close frame, like a crashed browser
+    loop {
+        tokio::select! {
+            frame = read.next() => {
+                let text = match frame {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let Some(frames) = dispatch(&state, &text) else { return };
+                for frame in frames {
+                    if write.send(WsMessage::Text(frame)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            push = pushes.recv() => match push {
+                Some(Push::Frame(frame)) => {
+                    if write.send(WsMessage::Text(frame)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Push::Disconnect) | None => return,
+            },
+        }
+    }
+    let _ = write.close().await;
+}
+
+/// Frames to send back for one command, or `None` to drop the connection
+fn dispatch(state: &Mutex<State>, text: &str) -> Option<Vec<String>> {
+    let Ok(frame) = serde_json::from_str::<Value>(text) else {
+        log::warn!("Mock CDP server ignoring malformed frame: {}", text);
+        return Some(Vec::new());
+    };
+    let request = Request {
+        id: frame.get("id").and_then(Value::as_i64).unwrap_or_default(),
+        method: frame.get("method").and_then(Value::as_str).unwrap_or_default().to_string(),
+        params: frame.get("params").cloned().unwrap_or_else(|| json!({})),
+        session_id: frame.get("sessionId").and_then(Value::as_str).map(str::to_string),
+    };
+
+    let (injected, handler) = {
+        let mut state = state.lock().unwrap();
+        state.received.push(request.clone());
+        let injected = state.injected.get_mut(&request.method).and_then(VecDeque::pop_front);
+        (injected, state.handlers.get(&request.method).cloned())
+    };
+    // Handlers run unlocked so they may call back into the server
+    let reply = match (injected, handler) {
+        (Some(reply), _) => reply,
+        (None, Some(handler)) => handler(&request),
+        (None, None) => state.lock().unwrap().respond(&request),
+    };
+
+    let mut response = match reply.outcome {
+        Outcome::Result(result) => json!({ "id": request.id, "result": result }),
+        Outcome::Error { code, message } => json!({ "id": request.id, "error": { "code": code, "message": message } }),
+        Outcome::Disconnect => return None,
+        Outcome::Silence => Value::Null,
+    };
+    let mut frames = Vec::new();
+    if !response.is_null() {
+        if let Some(session_id) = &request.session_id {
+            response["sessionId"] = json!(session_id);
+        }
+        frames.push(response.to_string());
+    }
+    for (method, params) in reply.events {
+        frames.push(event_frame(&method, params, request.session_id.as_deref()));
+    }
+    Some(frames)
+}
+
+fn event_frame(method: &str, params: Value, session_id: Option<&str>) -> String {
+    let mut event = json!({ "method": method, "params": params });
+    if let Some(session_id) = session_id {
+        event["sessionId"] = json!(session_id);
+    }
+    event.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// Sends `command` and collects whatever arrives until the line goes quiet
+    async fn call(socket: &mut Client, command: Value) -> Vec<Value> {
+        socket.send(WsMessage::Text(command.to_string())).await.unwrap();
+        let mut frames = Vec::new();
+        while let Ok(Some(Ok(WsMessage::Text(text)))) =
+            tokio::time::timeout(std::time::Duration::from_millis(100), socket.next()).await
+        {
+            frames.push(serde_json::from_str::<Value>(&text).unwrap());
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn test_scripted_browser_session() {
+        let server = MockCdpServer::start().await.unwrap();
+        let (mut socket, _) = connect_async(server.ws_url()).await.unwrap();
+
+        let attached = call(&mut socket, json!({ "id": 1, "method": "Target.attachToTarget", "params": { "targetId": INITIAL_TARGET_ID, "flatten": true } })).await;
+        assert_eq!(attached[0]["result"]["sessionId"], "SESSION-PAGE-1");
+        assert_eq!(attached[1]["method"], "Target.attachedToTarget");
+
+        let navigated = call(&mut socket, json!({ "id": 2, "method": "Page.navigate", "params": { "url": "https://example.com/" }, "sessionId": "SESSION-PAGE-1" })).await;
+        assert_eq!(navigated[0]["sessionId"], "SESSION-PAGE-1");
+        assert_eq!(navigated[2]["method"], "Page.loadEventFired");
+
+        let evaluated = call(&mut socket, json!({ "id": 3, "method": "Runtime.evaluate", "params": { "expression": "42" } })).await;
+        assert_eq!(evaluated[0]["result"]["result"]["value"], 42);
+
+        server.on("Runtime.evaluate", |request| Reply::result(json!({ "result": { "type": "string", "value": request.params["expression"] } })));
+        server.fail_next("Runtime.evaluate", -32000, "Execution context was destroyed.");
+        let failed = call(&mut socket, json!({ "id": 4, "method": "Runtime.evaluate", "params": { "expression": "document.title" } })).await;
+        assert_eq!(failed[0]["error"]["code"], -32000);
+        let handled = call(&mut socket, json!({ "id": 5, "method": "Runtime.evaluate", "params": { "expression": "document.title" } })).await;
+        assert_eq!(handled[0]["result"]["result"]["value"], "document.title");
+
+        server.disconnect_on("Browser.getVersion");
+        assert!(call(&mut socket, json!({ "id": 6, "method": "Browser.getVersion" })).await.is_empty());
+        assert_eq!(server.received().len(), 6);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::Deserialize;
+
+/// Wire-level traffic recording to a JSONL file, for post-mortems of flaky runs.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// File to record into (truncated on open). `None` disables recording.
+    pub path: Option<String>,
+    /// Frames longer than this are truncated in the recording.
+    pub max_frame_bytes: usize,
+    /// Recording stops once the file reaches this size.
+    pub max_file_bytes: u64,
+    /// JSON keys whose values are replaced with `<redacted>`, at any depth.
+    pub redact_fields: Vec<String>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_frame_bytes: 64 * 1024, // 64 KiB
+            max_file_bytes: 256 * 1024 * 1024, // 256 MiB
+            redact_fields: Vec::new(),
+        }
+    }
+}
+
+/// TLS settings for `wss://` connections. The defaults use the system trust store.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM file with extra root certificates, trusted in addition to the system ones.
+    pub ca_bundle_path: Option<String>,
+    /// PEM client certificate (chain) for mutual TLS. Requires `client_key_path`.
+    pub client_cert_path: Option<String>,
+    /// PEM PKCS#8 private key matching `client_cert_path`.
+    pub client_key_path: Option<String>,
+    /// Skip server certificate validation entirely. Only for test grids.
+    pub danger_accept_invalid_certs: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    pub max_message_size: Option<usize>,
+    pub accept_unmasked_frames: bool,
+    /// Interval between keepalive pings. `None` disables keepalive.
+    pub ping_interval_ms: Option<u64>,
+    /// How long the peer may stay silent after a ping before the connection is declared dead.
+    pub pong_timeout_ms: u64,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_message_size: Some(64 * 1024 * 1024), // 64 MiB
+            accept_unmasked_frames: false,
+            ping_interval_ms: Some(15_000), // 15 seconds
+            pong_timeout_ms: 10_000, // 10 seconds
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+// --- Transport Error (L3) ---
+#[derive(Error, Debug, Clone, PartialEq)] // Clone might be useful for some scenarios, e.g., state reporting
+pub enum TransportError {
+    #[error("Invalid URL format: {0}")]
+    InvalidUrl(String),
+
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(String),
+
+    #[error("Connection closed: {reason:?}")]
+    ConnectionClosed { reason: Option<String> },
+
+    #[error("Not connected")]
+    NotConnected,
+
+    #[error("I/O error: {0}")]
+    Io(String), // Wrap std/tokio IO error strings
+
+    #[error("TLS error: {0}")]
+    TlsError(String),
+
+    /// The server rejected the handshake credentials (HTTP 401/403).
+    #[error("Authentication failed (HTTP {status}): {detail}")]
+    AuthenticationFailed { status: u16, detail: String },
+
+    #[error("WebSocket protocol error: {0}")]
+    WebSocket(String), // Wrap tungstenite::Error strings (protocol related)
+
+    #[error("Failed to send message: {0}")]
+    SendFailed(String),
+
+    #[error("Failed to receive message: {0}")]
+    ReceiveFailed(String),
+
+    #[error("Operation timed out: {0}")]
+    Timeout(String), // Specific timeout details
+
+    #[error("Serialization/Deserialization error: {0}")]
+    Serde(String), // e.g., invalid UTF8, framing issues
+
+    #[error("Unsupported URL scheme: {0}")]
+    UnsupportedScheme(String),
+
+    #[error("Internal transport error: {0}")]
+    Internal(String),
+}
+
+// Needed by tokio-util codecs, whose error type must absorb I/O errors
+impl From<std::io::Error> for TransportError {
+    fn from(err: std::io::Error) -> Self {
+        TransportError::Io(err.to_string())
+    }
+}
@@ -0,0 +1,6 @@
+pub mod config;
+pub mod error;
+pub mod message;
+
+pub use error::TransportError;
+pub use message::{ConnectionId, IncomingRawMessage, SendRawMessage};
@@ -0,0 +1,21 @@
+use actix::prelude::*;
+use crate::error::TransportError;
+
+/// Unique ID for connections managed by the supervisor.
+pub type ConnectionId = u64;
+
+/// Message to send a raw string payload over the connection.
+/// Handled by ConnectionActor.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Result<(), TransportError>")] // Use TransportError
+pub struct SendRawMessage(pub String);
+
+/// Message representing a raw string payload received from the connection.
+/// Sent *by* ConnectionActor to a designated handler (e.g., Command/Event Actor).
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+pub struct IncomingRawMessage {
+    /// Connection the frame arrived on. Command ids are only unique per connection.
+    pub connection_id: ConnectionId,
+    pub raw: String,
+}
@@ -0,0 +1,274 @@
+//! The browser behind one connection and the targets attached to it.
+//!
+//! The `BrowserActor` discovers targets (`Target.setDiscoverTargets`), attaches to every
+//! page with a flattened session and starts a supervised `SessionActor` for it. The
+//! session's events are routed to it by the connection's `EventActor`.
+
+use super::session::{SessionActor, TargetDetached};
+use super::{CommandActor, ConnectionId, ConnectionLost, EventActor, EventFilter, ExecuteCommand, ProtocolEvent, Subscribe, SubscriptionId, Unsubscribe};
+use crate::error::ProtocolError;
+use actix::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+
+/// Target types that get a session as soon as they are discovered.
+const AUTO_ATTACH_TYPES: &[&str] = &["page"];
+
+/// An attached target and the actor serving its session.
+#[derive(Debug, Clone)]
+pub struct TargetSession {
+    pub target_id: String,
+    pub session_id: String,
+    pub target_type: String,
+    pub session: Addr<SessionActor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetInfo {
+    target_id: String,
+    #[serde(rename = "type")]
+    target_type: String,
+}
+
+type AttachResult = Result<TargetSession, ProtocolError>;
+
+// --- Messages ---
+
+/// Opens a new page at `url` and returns its session once attached.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Result<TargetSession, ProtocolError>")]
+pub struct NewPage {
+    pub url: String,
+}
+
+/// Every attached page, ordered by target id.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "Vec<TargetSession>")]
+pub struct GetPages;
+
+/// Runs a browser-level command (no session), e.g. `Browser.getVersion`.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Result<serde_json::Value, ProtocolError>")]
+pub struct BrowserCommand {
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+// --- Actor ---
+
+#[derive(Debug)]
+pub struct BrowserActor {
+    connection_id: ConnectionId,
+    commands: Addr<CommandActor>,
+    events: Addr<EventActor>,
+    targets: HashMap<String, (TargetSession, Option<SubscriptionId>)>,
+    /// Attaches in progress, with everyone waiting for them.
+    attaching: HashMap<String, Vec<oneshot::Sender<AttachResult>>>,
+    subscription: Option<SubscriptionId>,
+}
+
+impl BrowserActor {
+    pub fn new(connection_id: ConnectionId, commands: Addr<CommandActor>, events: Addr<EventActor>) -> Self {
+        Self { connection_id, commands, events, targets: HashMap::new(), attaching: HashMap::new(), subscription: None }
+    }
+
+    fn execute(&self, method: &str, params: serde_json::Value) -> impl std::future::Future<Output = Result<serde_json::Value, ProtocolError>> {
+        let request = self.commands.send(ExecuteCommand { session_id: None, method: method.to_string(), params, idempotent: false });
+        async move {
+            request.await.map_err(|e| ProtocolError::Internal(format!("Command actor unavailable: {}", e)))?
+        }
+    }
+
+    /// Attaches to `target_id` unless already attached; concurrent requests share one attach.
+    fn attach(&mut self, target_id: String, target_type: String, ctx: &mut Context<Self>) -> oneshot::Receiver<AttachResult> {
+        let (waiter, result) = oneshot::channel();
+        if let Some((target, _)) = self.targets.get(&target_id) {
+            let _ = waiter.send(Ok(target.clone()));
+            return result;
+        }
+        if let Some(waiters) = self.attaching.get_mut(&target_id) {
+            waiters.push(waiter);
+            return result;
+        }
+        self.attaching.insert(target_id.clone(), vec![waiter]);
+
+        let attach = self.execute("Target.attachToTarget", serde_json::json!({ "targetId": target_id, "flatten": true }));
+        ctx.spawn(attach.into_actor(self).map(move |attached, act, ctx| {
+            let outcome = attached.and_then(|result| {
+                result["sessionId"].as_str().map(str::to_string).ok_or_else(|| ProtocolError::ResponseParseError {
+                    reason: "Target.attachToTarget returned no sessionId".to_string(),
+                    response_fragment: result.to_string().chars().take(100).collect(),
+                })
+            });
+            let outcome = outcome.map(|session_id| act.start_session(target_id.clone(), session_id, target_type, ctx));
+            if let Err(e) = &outcome {
+                log::warn!("Browser {}: attaching to target {} failed: {}", act.connection_id, target_id, e);
+            }
+            for waiter in act.attaching.remove(&target_id).unwrap_or_default() {
+                let _ = waiter.send(outcome.clone());
+            }
+        }));
+        result
+    }
+
+    fn start_session(&mut self, target_id: String, session_id: String, target_type: String, ctx: &mut Context<Self>) -> TargetSession {
+        let commands = self.commands.clone();
+        let session = {
+            let (target_id, session_id, target_type) = (target_id.clone(), session_id.clone(), target_type.clone());
+            Supervisor::start(move |_| SessionActor::new(target_id, session_id, target_type, commands))
+        };
+        let target = TargetSession { target_id: target_id.clone(), session_id: session_id.clone(), target_type, session };
+        log::info!("Browser {}: attached to target {} (session {})", self.connection_id, target_id, session_id);
+
+        // Subscribe before the first command so no event of the session is missed
+        let subscribe = self.events.send(Subscribe {
+            filter: EventFilter::method("*").in_session(session_id),
+            recipient: target.session.clone().recipient(),
+        });
+        ctx.spawn(subscribe.into_actor(self).map(move |subscription, act, _ctx| match (subscription, act.targets.get_mut(&target_id)) {
+            (Ok(id), Some((_, slot))) => *slot = Some(id),
+            (Ok(id), None) => act.events.do_send(Unsubscribe(id)), // Detached meanwhile
+            (Err(e), _) => log::error!("Browser {}: cannot route events of target {}: {}", act.connection_id, target_id, e),
+        }));
+        self.targets.insert(target.target_id.clone(), (target.clone(), None));
+        target
+    }
+
+    fn detach(&mut self, target_id: &str) {
+        if let Some((target, subscription)) = self.targets.remove(target_id) {
+            log::info!("Browser {}: target {} detached", self.connection_id, target_id);
+            target.session.do_send(TargetDetached);
+            if let Some(id) = subscription {
+                self.events.do_send(Unsubscribe(id));
+            }
+        }
+    }
+}
+
+impl Actor for BrowserActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        log::info!("BrowserActor for connection {} started", self.connection_id);
+        // Browser-level target events carry no session id
+        let subscribe = self.events.send(Subscribe { filter: EventFilter::method("Target.*"), recipient: ctx.address().recipient() });
+        let discover = self.execute("Target.setDiscoverTargets", serde_json::json!({ "discover": true }));
+        ctx.spawn(
+            async move {
+                let subscription = subscribe.await;
+                // Existing targets are reported as `Target.targetCreated` once discovery is on
+                (subscription, discover.await)
+            }
+            .into_actor(self)
+            .map(|(subscription, discovered), act, _ctx| {
+                match subscription {
+                    Ok(id) => act.subscription = Some(id),
+                    Err(e) => log::error!("Browser {}: cannot subscribe to target events: {}", act.connection_id, e),
+                }
+                if let Err(e) = discovered {
+                    log::error!("Browser {}: target discovery failed: {}", act.connection_id, e);
+                }
+            }),
+        );
+    }
+
+    fn stopped(&mut self, _ctx: &mut Context<Self>) {
+        let targets: Vec<String> = self.targets.keys().cloned().collect();
+        for target_id in targets {
+            self.detach(&target_id);
+        }
+        if let Some(id) = self.subscription.take() {
+            self.events.do_send(Unsubscribe(id));
+        }
+        log::info!("BrowserActor for connection {} stopped", self.connection_id);
+    }
+}
+
+impl Handler<ProtocolEvent> for BrowserActor {
+    type Result = ();
+
+    fn handle(&mut self, event: ProtocolEvent, ctx: &mut Context<Self>) {
+        match event.method.as_str() {
+            "Target.targetCreated" => match TargetInfo::deserialize(&event.params["targetInfo"]) {
+                Ok(info) if AUTO_ATTACH_TYPES.contains(&info.target_type.as_str()) => {
+                    // Outcome is logged by `attach`
+                    drop(self.attach(info.target_id, info.target_type, ctx));
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Browser {}: malformed Target.targetCreated: {}", self.connection_id, e),
+            },
+            "Target.targetDestroyed" | "Target.detachedFromTarget" => {
+                match event.params["targetId"].as_str().map(str::to_string) {
+                    Some(target_id) => self.detach(&target_id),
+                    // Older browsers only name the session
+                    None => {
+                        let session_id = event.params["sessionId"].as_str();
+                        let target_id = self.targets.values()
+                            .find(|(target, _)| Some(target.session_id.as_str()) == session_id)
+                            .map(|(target, _)| target.target_id.clone());
+                        if let Some(target_id) = target_id {
+                            self.detach(&target_id);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Handler<ConnectionLost> for BrowserActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConnectionLost, ctx: &mut Context<Self>) {
+        log::warn!("Browser {}: connection lost ({}), detaching {} target(s)", self.connection_id, msg.0, self.targets.len());
+        ctx.stop(); // `stopped` detaches every session
+    }
+}
+
+impl Handler<NewPage> for BrowserActor {
+    type Result = ResponseActFuture<Self, Result<TargetSession, ProtocolError>>;
+
+    fn handle(&mut self, msg: NewPage, _ctx: &mut Context<Self>) -> Self::Result {
+        let create = self.execute("Target.createTarget", serde_json::json!({ "url": msg.url }));
+        Box::pin(
+            create.into_actor(self)
+                .then(|created, act, ctx| {
+                    let attached = created.and_then(|result| match result["targetId"].as_str() {
+                        Some(target_id) => Ok(act.attach(target_id.to_string(), "page".to_string(), ctx)),
+                        None => Err(ProtocolError::ResponseParseError {
+                            reason: "Target.createTarget returned no targetId".to_string(),
+                            response_fragment: result.to_string().chars().take(100).collect(),
+                        }),
+                    });
+                    async move {
+                        attached?.await.unwrap_or_else(|_| Err(ProtocolError::Internal("Browser actor stopped while attaching".to_string())))
+                    }
+                    .into_actor(act)
+                }),
+        )
+    }
+}
+
+impl Handler<GetPages> for BrowserActor {
+    type Result = MessageResult<GetPages>;
+
+    fn handle(&mut self, _msg: GetPages, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut pages: Vec<TargetSession> = self.targets.values()
+            .filter(|(target, _)| target.target_type == "page")
+            .map(|(target, _)| target.clone())
+            .collect();
+        pages.sort_by(|a, b| a.target_id.cmp(&b.target_id));
+        MessageResult(pages)
+    }
+}
+
+impl Handler<BrowserCommand> for BrowserActor {
+    type Result = ResponseFuture<Result<serde_json::Value, ProtocolError>>;
+
+    fn handle(&mut self, msg: BrowserCommand, _ctx: &mut Context<Self>) -> Self::Result {
+        Box::pin(self.execute(&msg.method, msg.params))
+    }
+}
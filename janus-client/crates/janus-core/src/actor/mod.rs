@@ -1,38 +1,43 @@
 use actix::prelude::*;
 // Use correct error types in message Results and Handlers
-use crate::error::{CoreError, TransportError, ProtocolError, MailboxError};
+use crate::error::{CoreError, TransportError, ProtocolError};
 use crate::config; // Import config if needed by SupervisorActor
+use crate::plugin::{PluginRegistry, Plugins};
+use rate_limit::RateLimiter;
+use retry::RetryPolicy;
 // Import necessary types from janus-transport
 // Adjust imports to use the new function signature and potentially specific actor type
-use janus_transport::{ConnectParams, ConnectionState, ConnectionStatusUpdate, create_transport_actor};
-use janus_transport::WebSocketTransport; // Assuming WebSocket is primary for now
-use janus_transport::ConnectionActor; // Import the concrete actor type if needed for Addr type
-use std::collections::HashMap;
-use url::Url; // Use the url crate
+use futures_util::future::join_all;
+use janus_transport::{CloseConnection, ConnectParams, ConnectionHandle, ConnectionMetricsSnapshot, ConnectionState, ConnectionStatusUpdate, Keepalive, TrafficRecorder, create_transport_actor, DEFAULT_CONNECT_TIMEOUT, REPLAY_ID_BASE};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
 
+pub mod browser;
+mod rate_limit;
+mod retry;
+pub mod session;
 
-// --- Common Actor Messages ---
+pub use browser::{BrowserActor, BrowserCommand, GetPages, NewPage, TargetSession};
+pub use session::{CloseTarget, ExecutionContext, FrameInfo, GetSessionState, SessionActor, SessionCommand, SessionState};
+pub use janus_common::message::{ConnectionId, IncomingRawMessage, SendRawMessage};
 
-/// Message to send a raw string payload over the connection.
-/// Handled by ConnectionActor.
-#[derive(Message, Debug, Clone)]
-#[rtype(result = "Result<(), TransportError>")] // Use TransportError
-pub struct SendRawMessage(pub String);
 
-/// Message representing a raw string payload received from the connection.
-/// Sent *by* ConnectionActor to a designated handler (e.g., Command/Event Actor).
-#[derive(Message, Debug, Clone)]
-#[rtype(result = "()")]
-pub struct IncomingRawMessage(pub String);
+// --- Common Actor Messages ---
 
 /// Internal representation of a command to be executed.
 /// Sent *to* CommandActor.
-#[derive(Message, Debug)]
+#[derive(Message, Debug, Clone)]
 #[rtype(result = "Result<serde_json::Value, ProtocolError>")] // Use ProtocolError
 pub struct ExecuteCommand {
-    pub target_id: Option<String>, // Or SessionId, context dependent
+    /// Flattened CDP session to run the command in; `None` for browser-level commands.
+    pub session_id: Option<String>,
     pub method: String,
     pub params: serde_json::Value,
+    /// The caller vouches that sending the command twice is harmless, so it may be retried.
+    pub idempotent: bool,
     // reply_to is implicit in actix request/response
 }
 
@@ -49,75 +54,827 @@ pub struct ProtocolEvent {
 // --- Placeholder Core Actors ---
 // Define them here or in separate modules (e.g., core/actor/command.rs)
 
+/// Tells a `CommandActor` or `BrowserActor` that its connection dropped, so responses
+/// to commands already sent will never arrive and sessions are gone.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+pub struct ConnectionLost(pub String);
+
+/// A command written to the connection and still awaiting its response.
 #[derive(Debug)]
-pub struct CommandActor;
-impl Actor for CommandActor { type Context = Context<Self>; }
-// Basic handler for IncomingRawMessage (replace with actual logic later)
+struct PendingCommand {
+    method: String,
+    responder: oneshot::Sender<Result<serde_json::Value, ProtocolError>>,
+    timeout: SpawnHandle,
+}
+
+type CommandResponseFuture = ResponseFuture<Result<serde_json::Value, ProtocolError>>;
+
+/// A command held back by the rate limit. Once released, its caller gets the future
+/// for its response.
+struct QueuedCommand {
+    command: ExecuteCommand,
+    release: oneshot::Sender<CommandResponseFuture>,
+}
+
+impl std::fmt::Debug for QueuedCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuedCommand").field("method", &self.command.method).finish()
+    }
+}
+
+/// The fields of a command response; `id` has already been matched by the router.
+#[derive(Deserialize)]
+struct CommandResponse {
+    id: u64,
+    result: Option<serde_json::Value>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+    data: Option<serde_json::Value>,
+}
+
+/// Correlates commands and responses for a single connection.
+///
+/// Each `ExecuteCommand` gets the next id on this connection and waits in the pending
+/// table until its response arrives, its timeout fires, or the connection is lost.
+/// Commands over the rate limit wait in a queue first and are sent in order.
+#[derive(Debug)]
+pub struct CommandActor {
+    connection_id: ConnectionId,
+    sender: Recipient<SendRawMessage>,
+    timeout: Duration,
+    next_id: u64,
+    pending: HashMap<u64, PendingCommand>,
+    /// Set by `Drain`; new commands are rejected from then on.
+    draining: bool,
+    drain_waiters: Vec<oneshot::Sender<()>>,
+    plugins: Plugins,
+    retry: RetryPolicy,
+    rate_limiter: RateLimiter,
+    queued: VecDeque<QueuedCommand>,
+    release_timer: Option<SpawnHandle>,
+}
+
+impl CommandActor {
+    pub fn new(connection_id: ConnectionId, sender: Recipient<SendRawMessage>, timeout: Duration) -> Self {
+        Self {
+            connection_id,
+            sender,
+            timeout,
+            next_id: 1,
+            pending: HashMap::new(),
+            draining: false,
+            drain_waiters: Vec::new(),
+            plugins: Plugins::default(),
+            retry: config::RetryConfig::default().into(),
+            rate_limiter: RateLimiter::default(),
+            queued: VecDeque::new(),
+            release_timer: None,
+        }
+    }
+
+    /// Paces the commands sent on this connection.
+    pub fn with_rate_limit(mut self, rate_limit: &config::RateLimitConfig) -> Self {
+        self.rate_limiter = RateLimiter::from(rate_limit);
+        self
+    }
+
+    /// Retries idempotent commands that fail transiently according to `retry`.
+    pub fn with_retry(mut self, retry: config::RetryConfig) -> Self {
+        self.retry = retry.into();
+        self
+    }
+
+    /// Runs every command through `plugins` before it is sent.
+    pub fn with_plugins(mut self, plugins: Plugins) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Executes `command` now if the rate limit allows it, otherwise queues it.
+    fn submit(&mut self, command: ExecuteCommand, ctx: &mut Context<Self>) -> CommandResponseFuture {
+        if self.queued.is_empty() && self.rate_limiter.try_acquire(&command.method).is_ok() {
+            return self.execute(command, ctx);
+        }
+        let (release, released) = oneshot::channel();
+        self.queued.push_back(QueuedCommand { command, release });
+        log::debug!("Connection {}: rate limit reached, {} command(s) queued", self.connection_id, self.queued.len());
+        if self.release_timer.is_none() {
+            self.release_queued(ctx);
+        }
+        Box::pin(async move {
+            match released.await {
+                Ok(response) => response.await,
+                Err(_) => Err(ProtocolError::ConnectionLost("command actor stopped".to_string())),
+            }
+        })
+    }
+
+    /// Executes queued commands for as long as the rate limit allows, then schedules the
+    /// next release.
+    fn release_queued(&mut self, ctx: &mut Context<Self>) {
+        self.release_timer = None;
+        while let Some(next) = self.queued.front() {
+            if next.release.is_closed() {
+                // The caller gave up waiting
+                self.queued.pop_front();
+                continue;
+            }
+            if let Err(wait) = self.rate_limiter.try_acquire(&next.command.method) {
+                self.release_timer = Some(ctx.run_later(wait, |act, ctx| act.release_queued(ctx)));
+                return;
+            }
+            if let Some(QueuedCommand { command, release }) = self.queued.pop_front() {
+                let response = self.execute(command, ctx);
+                let _ = release.send(response);
+            }
+        }
+        self.notify_if_drained();
+    }
+
+    /// The next free command id. Ids wrap before `REPLAY_ID_BASE`, which the connection
+    /// uses for the `.enable` commands it replays after a reconnect.
+    fn allocate_id(&mut self) -> u64 {
+        loop {
+            let id = self.next_id;
+            self.next_id = if id + 1 >= REPLAY_ID_BASE { 1 } else { id + 1 };
+            if !self.pending.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+
+    /// Writes `command` to the connection and returns a future for its response.
+    fn execute(&mut self, command: ExecuteCommand, ctx: &mut Context<Self>) -> CommandResponseFuture {
+        let id = self.allocate_id();
+
+        let mut frame = serde_json::json!({ "id": id, "method": command.method, "params": command.params });
+        if let Some(session_id) = command.session_id {
+            // Flattened CDP sessions address the target through `sessionId`
+            frame["sessionId"] = serde_json::Value::String(session_id);
+        }
+        let frame = frame.to_string();
+
+        let (responder, response) = oneshot::channel();
+        let timeout = ctx.run_later(self.timeout, move |act, ctx| {
+            if let Some(pending) = act.pending.get(&id) {
+                log::warn!("Connection {}: command {} ({}) timed out", act.connection_id, id, pending.method);
+                act.complete(id, Err(ProtocolError::Timeout), ctx);
+            }
+        });
+        self.pending.insert(id, PendingCommand { method: command.method, responder, timeout });
+
+        let sender = self.sender.clone();
+        let this = ctx.address();
+        Box::pin(async move {
+            let sent = match sender.send(SendRawMessage(frame)).await {
+                Ok(result) => result.map_err(ProtocolError::SendFailed),
+                Err(e) => Err(ProtocolError::ConnectionLost(format!("Connection actor unavailable: {}", e))),
+            };
+            if let Err(e) = sent {
+                // Never written, so no response can arrive; release the pending entry now
+                this.do_send(CommandFailed { id, error: e.clone() });
+                return Err(e);
+            }
+            response.await.unwrap_or_else(|_| Err(ProtocolError::ConnectionLost("command actor stopped".to_string())))
+        })
+    }
+
+    /// Runs `command` as `execute` does, sending it again after a backoff for as long as
+    /// it fails transiently and attempts are left. Each attempt is a new command with a new id.
+    fn execute_with_retry(&self, command: ExecuteCommand, ctx: &mut Context<Self>) -> CommandResponseFuture {
+        let this = ctx.address();
+        let retry = self.retry.clone();
+        let connection_id = self.connection_id;
+        Box::pin(async move {
+            let mut attempt = 1;
+            loop {
+                // Through the mailbox, so every attempt sees the actor's current state
+                let result = match this.send(RetryAttempt(command.clone())).await {
+                    Ok(result) => result,
+                    Err(_) => return Err(ProtocolError::ConnectionLost("command actor stopped".to_string())),
+                };
+                match result {
+                    Err(e) if attempt < retry.max_attempts() && retry.is_transient(&e) => {
+                        let delay = retry.backoff(attempt);
+                        log::warn!("Connection {}: {} failed on attempt {}/{} ({}), retrying in {:?}",
+                            connection_id, command.method, attempt, retry.max_attempts(), e, delay);
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    Err(e) if attempt > 1 => {
+                        log::warn!("Connection {}: {} failed on attempt {}/{}, giving up: {}", connection_id, command.method, attempt, retry.max_attempts(), e);
+                        return Err(e);
+                    }
+                    Ok(value) if attempt > 1 => {
+                        log::info!("Connection {}: {} succeeded on attempt {}", connection_id, command.method, attempt);
+                        return Ok(value);
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+
+    fn complete(&mut self, id: u64, result: Result<serde_json::Value, ProtocolError>, ctx: &mut Context<Self>) {
+        match self.pending.remove(&id) {
+            Some(pending) => {
+                ctx.cancel_future(pending.timeout);
+                // The requester may have given up already; nothing to do then
+                let _ = pending.responder.send(result);
+                self.notify_if_drained();
+            }
+            None => log::debug!("Connection {} got a response for unknown or expired command id {}", self.connection_id, id),
+        }
+    }
+
+    fn notify_if_drained(&mut self) {
+        if self.pending.is_empty() && self.queued.is_empty() {
+            for waiter in self.drain_waiters.drain(..) {
+                let _ = waiter.send(());
+            }
+        }
+    }
+
+    fn fail_all(&mut self, reason: &str, ctx: &mut Context<Self>) {
+        let count = self.pending.len() + self.queued.len();
+        if count > 0 {
+            log::warn!("Connection {}: failing {} pending command(s): {}", self.connection_id, count, reason);
+        }
+        for (_, pending) in self.pending.drain() {
+            ctx.cancel_future(pending.timeout);
+            let _ = pending.responder.send(Err(ProtocolError::ConnectionLost(reason.to_string())));
+        }
+        for queued in self.queued.drain(..) {
+            let error = ProtocolError::ConnectionLost(reason.to_string());
+            let _ = queued.release.send(Box::pin(async move { Err(error) }));
+        }
+        if let Some(timer) = self.release_timer.take() {
+            ctx.cancel_future(timer);
+        }
+        self.notify_if_drained();
+    }
+}
+
+impl Actor for CommandActor {
+    type Context = Context<Self>;
+
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        self.fail_all("command actor stopped", ctx);
+        Running::Stop
+    }
+}
+
+impl Handler<ExecuteCommand> for CommandActor {
+    type Result = ResponseFuture<Result<serde_json::Value, ProtocolError>>;
+
+    fn handle(&mut self, mut msg: ExecuteCommand, ctx: &mut Context<Self>) -> Self::Result {
+        if self.draining {
+            return Box::pin(async { Err(ProtocolError::ShuttingDown) });
+        }
+        if let Err(e) = self.plugins.on_command(&mut msg) {
+            return Box::pin(async move { Err(e) });
+        }
+        if let Some(plugin) = self.plugins.command_handler(&msg.method) {
+            let result = plugin.handle_command(&msg);
+            return Box::pin(async move { result });
+        }
+        if self.retry.is_retryable(&msg) {
+            return self.execute_with_retry(msg, ctx);
+        }
+        self.submit(msg, ctx)
+    }
+}
+
+/// Internal: one attempt of a retried command. Plugins have already seen it.
+#[derive(Message)]
+#[rtype(result = "Result<serde_json::Value, ProtocolError>")]
+struct RetryAttempt(ExecuteCommand);
+
+impl Handler<RetryAttempt> for CommandActor {
+    type Result = ResponseFuture<Result<serde_json::Value, ProtocolError>>;
+
+    fn handle(&mut self, msg: RetryAttempt, ctx: &mut Context<Self>) -> Self::Result {
+        if self.draining {
+            return Box::pin(async { Err(ProtocolError::ShuttingDown) });
+        }
+        self.submit(msg.0, ctx)
+    }
+}
+
+/// Internal: a command could not be written to the connection.
+#[derive(Message)]
+#[rtype(result = "()")]
+struct CommandFailed {
+    id: u64,
+    error: ProtocolError,
+}
+
+impl Handler<CommandFailed> for CommandActor {
+    type Result = ();
+    fn handle(&mut self, msg: CommandFailed, ctx: &mut Context<Self>) {
+        self.complete(msg.id, Err(msg.error), ctx);
+    }
+}
+
 impl Handler<IncomingRawMessage> for CommandActor {
     type Result = ();
-    fn handle(&mut self, msg: IncomingRawMessage, _ctx: &mut Context<Self>) {
-        log::debug!("Placeholder Command Actor received raw message: {}...", msg.0.chars().take(100).collect::<String>());
-        // In reality, this actor would parse the message, check if it's a response (has ID),
-        // find the pending command, and send the result back to the requester.
+    fn handle(&mut self, msg: IncomingRawMessage, ctx: &mut Context<Self>) {
+        if msg.connection_id != self.connection_id {
+            log::error!("CommandActor for connection {} dropping frame from connection {}", self.connection_id, msg.connection_id);
+            return;
+        }
+        let response = match serde_json::from_str::<CommandResponse>(&msg.raw) {
+            Ok(response) => response,
+            Err(e) => {
+                let error = ProtocolError::ResponseParseError {
+                    reason: e.to_string(),
+                    response_fragment: msg.raw.chars().take(100).collect(),
+                };
+                // Fail the command if its id can still be recovered, otherwise it times out
+                match serde_json::from_str::<serde_json::Value>(&msg.raw).ok().and_then(|v| v["id"].as_u64()) {
+                    Some(id) => self.complete(id, Err(error), ctx),
+                    None => log::error!("Connection {}: {}", self.connection_id, error),
+                }
+                return;
+            }
+        };
+        let result = match (response.error, response.result) {
+            (Some(ResponseError { code, message, data }), _) => Err(ProtocolError::BrowserError { code, message, data }),
+            (None, result) => Ok(result.unwrap_or(serde_json::Value::Null)),
+        };
+        self.complete(response.id, result, ctx);
+    }
+}
+
+/// Stops a `CommandActor` accepting commands and resolves once those in flight have
+/// finished, or after `timeout`, failing any still pending. With `close_browser`, then
+/// sends `Browser.close` and waits briefly for the browser to acknowledge it.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "()")]
+pub struct Drain {
+    pub timeout: Duration,
+    pub close_browser: bool,
+}
+
+/// How long `Drain` waits for the browser to acknowledge `Browser.close`.
+const BROWSER_CLOSE_GRACE: Duration = Duration::from_secs(2);
+
+impl Handler<Drain> for CommandActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: Drain, _ctx: &mut Context<Self>) -> Self::Result {
+        self.draining = true;
+        let drained = (!self.pending.is_empty() || !self.queued.is_empty()).then(|| {
+            let (waiter, drained) = oneshot::channel();
+            self.drain_waiters.push(waiter);
+            drained
+        });
+        let wait = async move {
+            match drained {
+                Some(drained) => tokio::time::timeout(msg.timeout, drained).await.is_ok(),
+                None => true,
+            }
+        };
+
+        Box::pin(
+            wait.into_actor(self)
+                .then(move |drained, act, ctx| {
+                    if !drained {
+                        act.fail_all("shutdown deadline reached", ctx);
+                    }
+                    let close = msg.close_browser.then(|| {
+                        log::info!("Connection {}: closing browser", act.connection_id);
+                        act.execute(ExecuteCommand { session_id: None, method: "Browser.close".to_string(), params: serde_json::json!({}), idempotent: false }, ctx)
+                    });
+                    let connection_id = act.connection_id;
+                    async move {
+                        let Some(close) = close else { return };
+                        // The browser may drop the connection instead of answering; that is fine too
+                        match tokio::time::timeout(BROWSER_CLOSE_GRACE, close).await {
+                            Ok(Ok(_)) | Ok(Err(ProtocolError::ConnectionLost(_))) => {}
+                            Ok(Err(e)) => log::warn!("Connection {}: Browser.close failed: {}", connection_id, e),
+                            Err(_) => log::warn!("Connection {}: browser did not acknowledge Browser.close", connection_id),
+                        }
+                    }
+                    .into_actor(act)
+                }),
+        )
+    }
+}
+
+impl Handler<ConnectionLost> for CommandActor {
+    type Result = ();
+    fn handle(&mut self, msg: ConnectionLost, ctx: &mut Context<Self>) {
+        self.fail_all(&msg.0, ctx);
+    }
+}
+
+/// Number of commands awaiting a response or queued by the rate limit.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "usize")]
+pub struct GetPendingCount;
+
+impl Handler<GetPendingCount> for CommandActor {
+    type Result = usize;
+    fn handle(&mut self, _msg: GetPendingCount, _ctx: &mut Context<Self>) -> usize {
+        self.pending.len() + self.queued.len()
+    }
+}
+
+/// Identifies an event or connection status subscription, for unsubscribing. Unique
+/// across connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(pub u64);
+
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Which events a subscription receives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    /// An exact event name (`"Page.loadEventFired"`), every event of a domain (`"Page.*"`),
+    /// or every event (`"*"`).
+    pub method: String,
+    /// Only events from this session; `None` matches events from any session or none.
+    pub session_id: Option<String>,
+}
+
+impl EventFilter {
+    pub fn method(method: impl Into<String>) -> Self {
+        Self { method: method.into(), session_id: None }
+    }
+
+    pub fn in_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn matches(&self, event: &ProtocolEvent) -> bool {
+        if self.session_id.is_some() && self.session_id != event.session_id {
+            return false;
+        }
+        match self.method.strip_suffix(".*") {
+            Some(domain) => event.method.strip_prefix(domain).is_some_and(|rest| rest.starts_with('.')),
+            None => self.method == "*" || self.method == event.method,
+        }
     }
 }
 
+/// Registers `recipient` for the events matching `filter` on one connection.
+#[derive(Message, Debug)]
+#[rtype(result = "SubscriptionId")]
+pub struct Subscribe {
+    pub filter: EventFilter,
+    pub recipient: Recipient<ProtocolEvent>,
+}
+
+/// Removes a subscription. Returns `false` if it was unknown or already removed.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "bool")]
+pub struct Unsubscribe(pub SubscriptionId);
+
+/// The fields of an event frame.
+#[derive(Deserialize)]
+struct EventFrame {
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+}
+
+/// Parses the events of a single connection and distributes them to subscribers.
+///
+/// Subscribers whose actor has stopped are removed the next time an event would reach them.
 #[derive(Debug)]
-pub struct EventActor;
+pub struct EventActor {
+    connection_id: ConnectionId,
+    subscriptions: Vec<(SubscriptionId, EventFilter, Recipient<ProtocolEvent>)>,
+    plugins: Plugins,
+}
+
+impl EventActor {
+    pub fn new(connection_id: ConnectionId) -> Self {
+        Self { connection_id, subscriptions: Vec::new(), plugins: Plugins::default() }
+    }
+
+    /// Runs every event through `plugins` before it reaches subscribers.
+    pub fn with_plugins(mut self, plugins: Plugins) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    fn dispatch(&mut self, event: ProtocolEvent) {
+        let connection_id = self.connection_id;
+        let mut delivered = false;
+        self.subscriptions.retain(|(id, filter, recipient)| {
+            if !filter.matches(&event) {
+                return true;
+            }
+            match recipient.try_send(event.clone()) {
+                Ok(()) => {
+                    delivered = true;
+                    true
+                }
+                Err(SendError::Full(_)) => {
+                    log::warn!("Connection {}: subscriber {:?} is backed up, dropping {}", connection_id, id, event.method);
+                    true
+                }
+                Err(SendError::Closed(_)) => {
+                    log::debug!("Connection {}: removing subscription {:?}, its recipient stopped", connection_id, id);
+                    false
+                }
+            }
+        });
+        if !delivered {
+            log::trace!("Connection {}: no subscriber for {}", connection_id, event.method);
+        }
+    }
+}
+
 impl Actor for EventActor { type Context = Context<Self>; }
-// Basic handler for IncomingRawMessage (replace with actual logic later)
+
 impl Handler<IncomingRawMessage> for EventActor {
     type Result = ();
     fn handle(&mut self, msg: IncomingRawMessage, _ctx: &mut Context<Self>) {
-         log::debug!("Placeholder Event Actor received raw message: {}...", msg.0.chars().take(100).collect::<String>());
-        // In reality, this actor would parse the message, check if it's an event,
-        // determine the event type, and forward it to subscribers.
+        if msg.connection_id != self.connection_id {
+            log::error!("EventActor for connection {} dropping frame from connection {}", self.connection_id, msg.connection_id);
+            return;
+        }
+        match serde_json::from_str::<EventFrame>(&msg.raw) {
+            Ok(EventFrame { method, params, session_id }) => {
+                let mut event = ProtocolEvent { session_id, method, params };
+                if self.plugins.on_event(&mut event) {
+                    self.dispatch(event);
+                }
+            }
+            Err(e) => {
+                let error = ProtocolError::EventParseError {
+                    reason: e.to_string(),
+                    event_fragment: msg.raw.chars().take(100).collect(),
+                };
+                log::error!("Connection {}: {}", self.connection_id, error);
+            }
+        }
+    }
+}
+
+impl Handler<Subscribe> for EventActor {
+    type Result = MessageResult<Subscribe>;
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Context<Self>) -> Self::Result {
+        let id = SubscriptionId(NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed));
+        log::debug!("Connection {}: subscription {:?} for {:?}", self.connection_id, id, msg.filter);
+        self.subscriptions.push((id, msg.filter, msg.recipient));
+        MessageResult(id)
+    }
+}
+
+impl Handler<Unsubscribe> for EventActor {
+    type Result = bool;
+    fn handle(&mut self, msg: Unsubscribe, _ctx: &mut Context<Self>) -> Self::Result {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|(id, _, _)| *id != msg.0);
+        self.subscriptions.len() < before
     }
 }
 // --- End Placeholder Actors ---
 
+/// Receives every frame of one connection and splits it: responses (frames carrying an
+/// `id`) go to that connection's `CommandActor`, everything else to its `EventActor`.
+#[derive(Debug)]
+pub struct FrameRouter {
+    connection_id: ConnectionId,
+    commands: Recipient<IncomingRawMessage>,
+    events: Recipient<IncomingRawMessage>,
+}
+
+impl FrameRouter {
+    pub fn new(connection_id: ConnectionId, commands: Recipient<IncomingRawMessage>, events: Recipient<IncomingRawMessage>) -> Self {
+        Self { connection_id, commands, events }
+    }
+}
+
+impl Actor for FrameRouter { type Context = Context<Self>; }
+
+impl Handler<IncomingRawMessage> for FrameRouter {
+    type Result = ();
+
+    fn handle(&mut self, msg: IncomingRawMessage, _ctx: &mut Context<Self>) {
+        // Only the presence of `id` matters; the rest of the frame is skipped, not parsed
+        #[derive(Deserialize)]
+        struct Envelope {
+            id: Option<serde::de::IgnoredAny>,
+        }
+        let is_response = serde_json::from_str::<Envelope>(&msg.raw).is_ok_and(|envelope| envelope.id.is_some());
+        let (destination, kind) = if is_response { (&self.commands, "response") } else { (&self.events, "event") };
+        if let Err(e) = destination.try_send(msg) {
+            log::error!("Connection {} dropping {}: {}", self.connection_id, kind, e);
+        }
+    }
+}
+
+/// The actors serving one managed connection.
+#[derive(Debug, Clone)]
+pub struct ConnectionRoute {
+    pub connection: ConnectionHandle,
+    pub commands: Addr<CommandActor>,
+    pub events: Addr<EventActor>,
+    /// Started once the connection is up, and replaced after a reconnect since the
+    /// browser's sessions do not survive the old socket.
+    pub browser: Option<Addr<BrowserActor>>,
+}
+
+
+/// A managed connection and what the supervisor knows about its state.
+#[derive(Debug)]
+struct ManagedConnection {
+    route: ConnectionRoute,
+    url: String,
+    state: ConnectionState,
+    launched: Instant,
+    last_error: Option<TransportError>,
+}
+
+/// A snapshot of one managed connection, see `ListConnections`.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    pub url: String,
+    pub state: ConnectionState,
+    /// Time since the connection was launched.
+    pub uptime: Duration,
+    /// Commands awaiting a response or queued by the rate limit.
+    pub pending_commands: usize,
+    /// The error behind the most recent connection loss or failed connect, if any.
+    pub last_error: Option<TransportError>,
+}
 
 // --- Supervisor Actor ---
 
-/// Unique ID for connections managed by the supervisor.
-pub type ConnectionId = u64; // Or String, UUID, etc.
 
 /// The top-level supervisor actor.
 #[derive(Debug)]
 pub struct SupervisorActor {
     config: Option<config::Config>, // Use qualified path
     next_connection_id: ConnectionId,
-    // Store recipients for status updates, mapping ID to Recipient
-    // Storing Addr<ConnectionActor<T>> directly is hard due to the generic T.
-    // Store the recipient which doesn't have the generic type parameter problem.
-    connections: HashMap<ConnectionId, Recipient<ConnectionStatusUpdate>>,
-    // Store addresses of core actors needed by connections
-    command_actor_addr: Option<Addr<CommandActor>>,
-    event_actor_addr: Option<Addr<EventActor>>,
+    // Storing Addr<ConnectionActor<T>> directly is hard due to the generic T,
+    // so keep the transport-independent handle instead, alongside the
+    // connection's own command and event actors.
+    connections: HashMap<ConnectionId, ManagedConnection>,
+    /// Recipients of `ConnectionStatusUpdate`s, for one connection or all of them.
+    status_subscribers: Vec<(SubscriptionId, Option<ConnectionId>, Recipient<ConnectionStatusUpdate>)>,
+    shutdown: ShutdownPhase,
+    /// Registered plugins, loaded against the config in `started`.
+    registry: Option<PluginRegistry>,
+    plugins: Plugins,
+    /// The `[transport.recording]` file, opened by the first connection and shared by all.
+    recorder: Option<TrafficRecorder>,
     // TODO: Store BrowserActor addresses, plugin manager actor etc.
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownPhase {
+    Running,
+    Draining,
+    Done,
+}
+
 impl SupervisorActor {
     pub fn new(config: Option<config::Config>) -> Self {
         Self {
             config,
             next_connection_id: 0,
             connections: HashMap::new(),
-            command_actor_addr: None,
-            event_actor_addr: None,
+            status_subscribers: Vec::new(),
+            shutdown: ShutdownPhase::Running,
+            registry: None,
+            plugins: Plugins::default(),
+            recorder: None,
+        }
+    }
+
+    /// Plugins to load at startup, subject to the `[plugins.<name>]` config sections.
+    pub fn with_plugins(mut self, registry: PluginRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    fn subscribe_status(&mut self, connection: Option<ConnectionId>, recipient: Recipient<ConnectionStatusUpdate>) -> SubscriptionId {
+        let id = SubscriptionId(NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed));
+        self.status_subscribers.push((id, connection, recipient));
+        id
+    }
+
+    /// Forwards `update` to its subscribers, removing those that have stopped.
+    fn notify_status(&mut self, update: &ConnectionStatusUpdate) {
+        self.status_subscribers.retain(|(id, connection, recipient)| {
+            if connection.is_some_and(|connection| connection != update.id) {
+                return true;
+            }
+            match recipient.try_send(update.clone()) {
+                Ok(()) => true,
+                Err(SendError::Full(_)) => {
+                    log::warn!("Status subscriber {:?} is full, dropping update for connection ID {}", id, update.id);
+                    true
+                }
+                Err(SendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// Snapshot of a connection; asks its command actor for the pending count.
+    fn connection_info(id: ConnectionId, managed: &ManagedConnection) -> impl std::future::Future<Output = ConnectionInfo> {
+        let pending = managed.route.commands.send(GetPendingCount);
+        let mut info = ConnectionInfo {
+            id,
+            url: managed.url.clone(),
+            state: managed.state.clone(),
+            uptime: managed.launched.elapsed(),
+            pending_commands: 0,
+            last_error: managed.last_error.clone(),
+        };
+        async move {
+            // A stopped command actor has nothing pending
+            info.pending_commands = pending.await.unwrap_or(0);
+            info
+        }
+    }
+
+    /// Fills in the transport settings `params` leaves unset from the `[transport]` section.
+    fn apply_transport_config(&mut self, params: &mut ConnectParams) -> Result<(), CoreError> {
+        let Some(transport) = self.config.as_ref().map(|c| &c.transport) else { return Ok(()) };
+        if params.connect_timeout == DEFAULT_CONNECT_TIMEOUT {
+            params.connect_timeout = Duration::from_millis(transport.connect_timeout_ms);
+        }
+        if params.keepalive.is_none() {
+            params.keepalive = Keepalive::from_config(&transport.websocket);
         }
+        if params.tls == config::TlsConfig::default() {
+            params.tls = transport.tls.clone();
+        }
+        if params.recorder.is_none() {
+            if self.recorder.is_none() {
+                self.recorder = TrafficRecorder::from_config(&transport.recording)?;
+            }
+            params.recorder = self.recorder.clone();
+        }
+        Ok(())
+    }
+
+    fn shutdown_config(&self) -> config::ShutdownConfig {
+        self.config.as_ref().map(|c| c.actor_system.shutdown.clone()).unwrap_or_default()
     }
 
-    // Helper to determine where incoming messages from a ConnectionActor should be routed.
-    // This is a simplification; a real implementation might involve a dedicated RouterActor
-    // or more sophisticated logic within CommandActor/EventActor to handle mixed streams.
-    fn get_message_handler_recipient(&self) -> Recipient<IncomingRawMessage> {
-        // TODO: Implement proper routing logic. For now, send everything to CommandActor.
-        // This assumes CommandActor is responsible for differentiating responses vs events initially.
-        self.command_actor_addr.as_ref()
-            .expect("CommandActor address not available in supervisor")
-            .clone()
-            .recipient()
+    /// Drains every connection's commands, then closes the connections, all in parallel.
+    /// Resolves immediately if a shutdown already started.
+    fn shutdown(&mut self, msg: Shutdown) -> ResponseActFuture<Self, ()> {
+        if self.shutdown != ShutdownPhase::Running {
+            log::debug!("Shutdown already {:?}.", self.shutdown);
+            return Box::pin(fut::ready(()));
+        }
+        self.shutdown = ShutdownPhase::Draining;
+
+        let routes: Vec<ConnectionRoute> = self.connections.values().map(|managed| managed.route.clone()).collect();
+        log::info!("Shutting down {} connection(s), waiting up to {:?} for commands in flight.", routes.len(), msg.drain_timeout);
+        let drain = Drain { timeout: msg.drain_timeout, close_browser: msg.close_browsers };
+        let close_all = join_all(routes.into_iter().map(move |route| async move {
+            let connection_id = route.connection.id;
+            // Drain before closing: responses can only arrive while the connection is open
+            if let Err(e) = route.commands.send(drain).await {
+                log::warn!("Could not drain commands of connection ID {}: {}", connection_id, e);
+            }
+            if let Err(e) = route.connection.closer.send(CloseConnection).await {
+                // Already stopped on its own
+                log::debug!("Could not close connection ID {}: {}", connection_id, e);
+            }
+        }));
+
+        Box::pin(close_all.into_actor(self).map(|_, act, _ctx| {
+            act.connections.clear();
+            act.shutdown = ShutdownPhase::Done;
+            log::info!("Shutdown complete.");
+        }))
+    }
+}
+
+/// Resolves with the signal's name once the process is asked to terminate.
+async fn termination_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|_| "Ctrl-C")
     }
 }
 
@@ -126,27 +883,52 @@ impl Actor for SupervisorActor {
 
     fn started(&mut self, ctx: &mut Context<Self>) {
         log::info!("SupervisorActor started.");
-        // Start core actors needed immediately
-        log::info!("Starting core actors...");
-        self.command_actor_addr = Some(CommandActor{}.start());
-        self.event_actor_addr = Some(EventActor{}.start());
-        log::info!("CommandActor and EventActor started.");
+        // Command and event actors are started per connection by LaunchConnection
 
-        // TODO: Load plugins, initialize monitoring, etc. based on config
+        if let Some(registry) = self.registry.take() {
+            let empty = HashMap::new();
+            self.plugins = registry.load(self.config.as_ref().map_or(&empty, |c| &c.plugins));
+        }
+
+        // TODO: Initialize monitoring, etc. based on config
 
         // TODO: Implement supervision strategies for core actors if needed.
         // By default, actix restarts actors on panic unless configured otherwise.
 
-        // Register SupervisorActor to handle system signals like SIGINT/SIGTERM for graceful shutdown
-        // Example: ctx.signals().registry().register(Signal::Interrupt, ctx.address().recipient());
-        // Requires actix `signal` feature
+        // Shut down gracefully on SIGINT/SIGTERM, then stop the whole system
+        if self.shutdown_config().handle_signals {
+            ctx.spawn(termination_signal().into_actor(self).then(|signal, act, _ctx| -> ResponseActFuture<Self, ()> {
+                match signal {
+                    Ok(name) => log::info!("Received {}, shutting down.", name),
+                    Err(e) => {
+                        log::error!("Cannot listen for termination signals: {}", e);
+                        return Box::pin(fut::ready(()));
+                    }
+                }
+                let shutdown = act.shutdown(Shutdown::from(&act.shutdown_config()));
+                Box::pin(shutdown.map(|_, _act, ctx| {
+                    ctx.stop();
+                    System::current().stop();
+                }))
+            }));
+        }
     }
 
-    fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
-        log::info!("SupervisorActor stopping...");
-        // TODO: Initiate graceful shutdown of child actors (Connections, BrowserActors etc.)
-        // Send stop messages to CommandActor, EventActor etc.
-        Running::Stop
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        match self.shutdown {
+            // Stopped without a `Shutdown`: close the children first, then stop for real
+            ShutdownPhase::Running if !self.connections.is_empty() => {
+                log::info!("SupervisorActor stopping, shutting down connections first...");
+                let shutdown = self.shutdown(Shutdown::from(&self.shutdown_config()));
+                ctx.spawn(shutdown.map(|_, _act, ctx| ctx.stop()));
+                Running::Continue
+            }
+            ShutdownPhase::Draining => Running::Continue, // Stops once the drain completes
+            _ => {
+                log::info!("SupervisorActor stopping...");
+                Running::Stop
+            }
+        }
     }
 }
 
@@ -157,26 +939,114 @@ impl Actor for SupervisorActor {
 #[rtype(result = "Result<ConnectionId, CoreError>")] // Use CoreError
 pub struct LaunchConnection {
     pub params: ConnectParams,
-    // pub owner_id: String, // Optional: Identifier for what owns this connection (e.g., browser instance ID)
+    /// The `[browsers.<name>]` section with per-browser settings, such as rate limits.
+    pub browser: Option<String>,
+    /// Receives every status update of the connection, starting with the first.
+    pub owner: Option<Recipient<ConnectionStatusUpdate>>,
+}
+
+/// Query for traffic and latency metrics of managed connections.
+/// `None` returns every connection, ordered by ID; an unknown ID returns an empty list.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Vec<ConnectionMetricsSnapshot>")]
+pub struct GetMetrics(pub Option<ConnectionId>);
+
+/// Shuts the actor tree down in order: new commands are rejected, commands in flight get
+/// up to `drain_timeout` to finish, then every connection is closed and the supervisor stops.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "()")]
+pub struct Shutdown {
+    pub drain_timeout: Duration,
+    /// Send `Browser.close` to every browser before closing its connection.
+    pub close_browsers: bool,
+}
+
+impl From<&config::ShutdownConfig> for Shutdown {
+    fn from(config: &config::ShutdownConfig) -> Self {
+        Self { drain_timeout: Duration::from_millis(config.drain_timeout_ms), close_browsers: config.close_browsers }
+    }
+}
+
+/// Every managed connection, ordered by ID.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "Vec<ConnectionInfo>")]
+pub struct ListConnections;
+
+/// A single managed connection; `None` if the ID is unknown or the connection is gone.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "Option<ConnectionInfo>")]
+pub struct GetConnection(pub ConnectionId);
+
+/// Forwards `ConnectionStatusUpdate`s to `recipient`: those of `connection`, or of every
+/// connection if `None`. Subscriptions to one connection end when it disconnects.
+#[derive(Message, Debug)]
+#[rtype(result = "SubscriptionId")]
+pub struct SubscribeConnectionStatus {
+    pub connection: Option<ConnectionId>,
+    pub recipient: Recipient<ConnectionStatusUpdate>,
 }
 
+/// Removes a status subscription. Returns `false` if it was unknown or already removed.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "bool")]
+pub struct UnsubscribeConnectionStatus(pub SubscriptionId);
+
+/// Looks up the actors serving a managed connection, e.g. to execute commands on it.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Option<ConnectionRoute>")]
+pub struct GetConnectionRoute(pub ConnectionId);
+
 // --- Supervisor Handlers ---
 
 impl Handler<LaunchConnection> for SupervisorActor {
     type Result = Result<ConnectionId, CoreError>; // Use CoreError
 
     fn handle(&mut self, msg: LaunchConnection, ctx: &mut Context<Self>) -> Self::Result {
-        let params = msg.params;
+        let mut params = msg.params;
         log::info!("Supervisor handling LaunchConnection request for URL: {}", params.url);
 
+        if self.shutdown != ShutdownPhase::Running {
+            return Err(CoreError::ActorSystem("Supervisor is shutting down".to_string()));
+        }
+
         // 1. Validate URL (parsing happens in create_transport_actor now)
         // Optional: Pre-validate scheme here if desired before calling factory
 
         let connection_id = self.next_connection_id;
         self.next_connection_id += 1;
 
-        // 2. Get Handler Recipient for Incoming Messages
-        let message_handler_recipient = self.get_message_handler_recipient();
+        self.apply_transport_config(&mut params)?;
+        let browser_config = msg.browser.as_ref().and_then(|name| {
+            let browser_config = self.config.as_ref().and_then(|c| c.browsers.get(name));
+            if browser_config.is_none() {
+                log::warn!("No [browsers.{}] section for connection {}, using defaults", name, connection_id);
+            }
+            browser_config
+        });
+        if let Some(browser_config) = browser_config {
+            // Headers and query parameters set on the request win over the configured ones
+            for (name, value) in &browser_config.headers {
+                // Header names are case-insensitive
+                if !params.headers.keys().any(|set| set.eq_ignore_ascii_case(name)) {
+                    params.headers.insert(name.clone(), value.clone());
+                }
+            }
+            for (name, value) in &browser_config.query_params {
+                params.query_params.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
+        // 2. Start this connection's own command and event actors, fed by a router,
+        //    so responses from different browsers can never be matched against each other.
+        //    The command actor needs the connection's sender, which only exists once the
+        //    transport actor is created, so the contexts are created now and only run once
+        //    the transport actor exists; if it cannot be created, none of them is started.
+        let command_ctx = Context::<CommandActor>::new();
+        let commands = command_ctx.address();
+        let events_ctx = Context::<EventActor>::new();
+        let events = events_ctx.address();
+        let router_ctx = Context::<FrameRouter>::new();
+        let message_handler_recipient = router_ctx.address().recipient();
 
         // 3. Get Supervisor Recipient (for status updates back to self)
         let supervisor_recipient = ctx.address().recipient::<ConnectionStatusUpdate>();
@@ -185,39 +1055,141 @@ impl Handler<LaunchConnection> for SupervisorActor {
         log::info!("Requesting transport actor creation for ID: {}", connection_id);
 
         // Use the factory function, passing the ID
-        // Note: create_transport_actor now returns Result<Addr<ConnectionActor<WebSocketTransport>>, TransportError>
+        // Note: create_transport_actor returns Result<ConnectionHandle, TransportError>
         // We need to map TransportError to CoreError.
-        let connection_addr = create_transport_actor(
+        let connection = create_transport_actor(
             connection_id,
             params.clone(), // Clone ConnectParams for the factory
             message_handler_recipient,
             Some(supervisor_recipient), // Pass supervisor recipient for status updates
         ).map_err(CoreError::Transport)?; // Map TransportError -> CoreError::Transport
 
-        log::info!("Transport actor (ID: {}) successfully started. Addr: {:?}", connection_id, connection_addr);
+        log::info!("Transport actor (ID: {}) successfully started. Handle: {:?}", connection_id, connection);
+        events_ctx.run(EventActor::new(connection_id).with_plugins(self.plugins.clone()));
+        router_ctx.run(FrameRouter::new(connection_id, commands.clone().recipient(), events.clone().recipient()));
+
+        let command_timeout = Duration::from_millis(
+            self.config.as_ref().map_or_else(|| config::GlobalConfig::default().default_command_timeout_ms, |c| c.global.default_command_timeout_ms),
+        );
+        let retry = self.config.as_ref().map(|c| c.global.command_retry.clone()).unwrap_or_default();
+        let rate_limit = browser_config.map(|b| b.rate_limit.clone()).unwrap_or_default();
+        command_ctx.run(
+            CommandActor::new(connection_id, connection.sender.clone(), command_timeout)
+                .with_plugins(self.plugins.clone())
+                .with_retry(retry)
+                .with_rate_limit(&rate_limit),
+        );
 
-        // Store the recipient for status updates, associated with the ID
-        self.connections.insert(connection_id, connection_addr.recipient::<ConnectionStatusUpdate>());
+        // Store the handle and its actors, associated with the ID
+        let route = ConnectionRoute { connection, commands, events, browser: None };
+        self.connections.insert(connection_id, ManagedConnection {
+            route,
+            url: params.url.clone(),
+            state: ConnectionState::Idle,
+            launched: Instant::now(),
+            last_error: None,
+        });
+        // Before any status update can be handled, so the owner sees all of them
+        if let Some(owner) = msg.owner {
+            self.subscribe_status(Some(connection_id), owner);
+        }
 
         Ok(connection_id) // Return the ID on success
     }
 }
 
+impl Handler<GetMetrics> for SupervisorActor {
+    type Result = MessageResult<GetMetrics>;
+
+    fn handle(&mut self, msg: GetMetrics, _ctx: &mut Context<Self>) -> Self::Result {
+        // Handles share counters with their actors, so no round trip to each connection is needed
+        let mut snapshots: Vec<ConnectionMetricsSnapshot> = self.connections.iter()
+            .filter(|(id, _)| msg.0.is_none() || msg.0 == Some(**id))
+            .map(|(_, managed)| managed.route.connection.metrics.snapshot())
+            .collect();
+        snapshots.sort_by_key(|snapshot| snapshot.connection_id);
+        MessageResult(snapshots)
+    }
+}
+
+impl Handler<Shutdown> for SupervisorActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: Shutdown, _ctx: &mut Context<Self>) -> Self::Result {
+        Box::pin(self.shutdown(msg).map(|_, _act, ctx| ctx.stop()))
+    }
+}
+
+impl Handler<ListConnections> for SupervisorActor {
+    type Result = ResponseFuture<Vec<ConnectionInfo>>;
+
+    fn handle(&mut self, _msg: ListConnections, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut ids: Vec<ConnectionId> = self.connections.keys().copied().collect();
+        ids.sort_unstable();
+        let infos: Vec<_> = ids.iter().map(|id| Self::connection_info(*id, &self.connections[id])).collect();
+        Box::pin(join_all(infos))
+    }
+}
+
+impl Handler<GetConnection> for SupervisorActor {
+    type Result = ResponseFuture<Option<ConnectionInfo>>;
+
+    fn handle(&mut self, msg: GetConnection, _ctx: &mut Context<Self>) -> Self::Result {
+        let info = self.connections.get(&msg.0).map(|managed| Self::connection_info(msg.0, managed));
+        Box::pin(async move {
+            match info {
+                Some(info) => Some(info.await),
+                None => None,
+            }
+        })
+    }
+}
+
+impl Handler<SubscribeConnectionStatus> for SupervisorActor {
+    type Result = MessageResult<SubscribeConnectionStatus>;
+
+    fn handle(&mut self, msg: SubscribeConnectionStatus, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.subscribe_status(msg.connection, msg.recipient))
+    }
+}
+
+impl Handler<UnsubscribeConnectionStatus> for SupervisorActor {
+    type Result = bool;
+
+    fn handle(&mut self, msg: UnsubscribeConnectionStatus, _ctx: &mut Context<Self>) -> bool {
+        let before = self.status_subscribers.len();
+        self.status_subscribers.retain(|(id, _, _)| *id != msg.0);
+        self.status_subscribers.len() != before
+    }
+}
+
+impl Handler<GetConnectionRoute> for SupervisorActor {
+    type Result = Option<ConnectionRoute>;
+
+    fn handle(&mut self, msg: GetConnectionRoute, _ctx: &mut Context<Self>) -> Self::Result {
+        self.connections.get(&msg.0).map(|managed| managed.route.clone())
+    }
+}
+
 // Handler for status updates coming FROM ConnectionActors managed by this supervisor
 impl Handler<ConnectionStatusUpdate> for SupervisorActor {
     type Result = ();
 
     fn handle(&mut self, msg: ConnectionStatusUpdate, _ctx: &mut Context<Self>) {
-        // Message now contains the ID: msg = ConnectionStatusUpdate { id: ConnectionId, state: ConnectionState }
+        // msg = ConnectionStatusUpdate { id: ConnectionId, state: ConnectionState, queue_depth: usize, last_error: Option<TransportError> }
         let connection_id = msg.id;
-        let new_state = msg.state;
+        let new_state = msg.state.clone();
 
         log::info!("Supervisor received status update for Connection ID {}: {:?}", connection_id, new_state);
 
         // Check if we are actually tracking this connection ID
-        if !self.connections.contains_key(&connection_id) {
+        let Some(managed) = self.connections.get_mut(&connection_id) else {
             log::warn!("Received status update for unknown or already removed Connection ID: {}", connection_id);
             return;
+        };
+        managed.state = new_state.clone();
+        if msg.last_error.is_some() {
+            managed.last_error = msg.last_error.clone();
         }
 
         match new_state {
@@ -226,21 +1198,51 @@ impl Handler<ConnectionStatusUpdate> for SupervisorActor {
                 if let Some(error) = maybe_error {
                     log::error!("Disconnection reason for ID {}: {}", connection_id, error);
                 }
-                // Remove the connection recipient from the map
-                if self.connections.remove(&connection_id).is_some() {
+                // Remove the connection handle from the map, failing its commands still in flight
+                if let Some(ManagedConnection { route, .. }) = self.connections.remove(&connection_id) {
+                    let reason = maybe_error.as_ref().map_or_else(|| "connection closed".to_string(), |e| e.to_string());
+                    if let Some(browser) = &route.browser {
+                        browser.do_send(ConnectionLost(reason.clone()));
+                    }
+                    route.commands.do_send(ConnectionLost(reason));
                     log::info!("Removed connection ID {} from supervisor map.", connection_id);
                 } else {
                     // Should not happen due to the contains_key check, but good to log
                     log::warn!("Attempted to remove connection ID {} but it was not found (race condition?).", connection_id);
                 }
-                // TODO: Notify the original owner/requester of this connection ID if applicable.
             }
             ConnectionState::Connected => {
-                 log::info!("Connection ID {} is now connected.", connection_id);
-                 // Potentially notify owner.
+                 // Repeated Connected updates report outbound queue saturation and drain
+                 log::info!("Connection ID {} is connected (outbound queue depth {}).", connection_id, msg.queue_depth);
+                 if let Some(ManagedConnection { route, .. }) = self.connections.get_mut(&connection_id) {
+                     if route.browser.is_none() && self.shutdown == ShutdownPhase::Running {
+                         route.browser = Some(BrowserActor::new(connection_id, route.commands.clone(), route.events.clone()).start());
+                     }
+                 }
+            }
+            ConnectionState::Reconnecting { attempt } => {
+                 // Keep the connection registered; the actor reports Disconnected if it gives up
+                 log::warn!("Connection ID {} lost, reconnect attempt {} in progress.", connection_id, attempt);
+                 // Responses to commands sent on the old socket will never arrive
+                 if attempt == 1 {
+                     if let Some(ManagedConnection { route, .. }) = self.connections.get_mut(&connection_id) {
+                         let lost = ConnectionLost("connection lost, reconnecting".to_string());
+                         route.commands.do_send(lost.clone());
+                         // Sessions die with the socket; a fresh BrowserActor rediscovers targets once reconnected
+                         if let Some(browser) = route.browser.take() {
+                             browser.do_send(lost);
+                         }
+                     }
+                 }
             }
             _ => { /* Connecting, Disconnecting - informational logging handled by the ConnectionActor */ }
         }
+
+        self.notify_status(&msg);
+        if matches!(msg.state, ConnectionState::Disconnected(_)) {
+            // IDs are never reused, so subscriptions to this connection are done
+            self.status_subscribers.retain(|(_, connection, _)| *connection != Some(connection_id));
+        }
     }
 }
 
@@ -249,3 +1251,654 @@ impl Handler<ConnectionStatusUpdate> for SupervisorActor {
 // If BrowserActor logic needs to be initiated by the supervisor,
 // a new message like `LaunchBrowserInstance` would be added, handled here,
 // which might internally call `LaunchConnection`.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::Plugin;
+    use mock_cdp::{MockCdpServer, Reply};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    /// Launches a supervised connection to `server` and waits for its browser actor.
+    pub(super) async fn launch(server: &MockCdpServer) -> (Addr<SupervisorActor>, ConnectionRoute) {
+        launch_with(server, SupervisorActor::new(None)).await
+    }
+
+    /// Like `launch`, on `supervisor`. The connection uses the `[browsers.test]` section if
+    /// the supervisor's config has one.
+    async fn launch_with(server: &MockCdpServer, supervisor: SupervisorActor) -> (Addr<SupervisorActor>, ConnectionRoute) {
+        let browser = supervisor.config.as_ref().is_some_and(|c| c.browsers.contains_key("test")).then(|| "test".to_string());
+        let supervisor = supervisor.start();
+        let route = launch_on(&supervisor, server, browser).await;
+        (supervisor, route)
+    }
+
+    /// Launches another connection to `server` on a running supervisor and waits for its browser actor.
+    async fn launch_on(supervisor: &Addr<SupervisorActor>, server: &MockCdpServer, browser: Option<String>) -> ConnectionRoute {
+        let params = ConnectParams::new(server.ws_url());
+        let id = supervisor.send(LaunchConnection { params, browser, owner: None }).await.unwrap().unwrap();
+        for _ in 0..100 {
+            if let Some(route) = supervisor.send(GetConnectionRoute(id)).await.unwrap() {
+                if route.browser.is_some() {
+                    return route;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("browser actor never started");
+    }
+
+    /// Waits until the commands the browser actor sends on startup have been answered.
+    async fn settled(commands: &Addr<CommandActor>) {
+        for _ in 0..100 {
+            if commands.send(GetPendingCount).await.unwrap() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("commands still pending");
+    }
+
+    fn command(method: &str) -> ExecuteCommand {
+        ExecuteCommand { session_id: None, method: method.to_string(), params: serde_json::json!({}), idempotent: false }
+    }
+
+    /// Forwards the events it receives to a channel.
+    struct Forward(tokio::sync::mpsc::UnboundedSender<ProtocolEvent>);
+
+    impl Actor for Forward { type Context = Context<Self>; }
+
+    impl Handler<ProtocolEvent> for Forward {
+        type Result = ();
+        fn handle(&mut self, event: ProtocolEvent, _ctx: &mut Context<Self>) {
+            let _ = self.0.send(event);
+        }
+    }
+
+    fn forward() -> (Recipient<ProtocolEvent>, tokio::sync::mpsc::UnboundedReceiver<ProtocolEvent>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (Forward(tx).start().recipient(), rx)
+    }
+
+    async fn next_event(rx: &mut tokio::sync::mpsc::UnboundedReceiver<ProtocolEvent>) -> ProtocolEvent {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap()
+    }
+
+    /// Adds its name to the `plugins` param of every command, answers `Janus.*` commands
+    /// and drops `Noise.*` events.
+    struct Stamp(&'static str);
+
+    impl Plugin for Stamp {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn domains(&self) -> Vec<String> {
+            vec!["Janus".to_string()]
+        }
+
+        fn handle_command(&self, _command: &ExecuteCommand) -> Result<serde_json::Value, ProtocolError> {
+            Ok(serde_json::json!({ "answeredBy": self.0 }))
+        }
+
+        fn on_command(&self, command: &mut ExecuteCommand) -> Result<(), ProtocolError> {
+            match &mut command.params["plugins"] {
+                serde_json::Value::Array(plugins) => plugins.push(self.0.into()),
+                plugins => *plugins = serde_json::json!([self.0]),
+            }
+            Ok(())
+        }
+
+        fn on_event(&self, event: &mut ProtocolEvent) -> bool {
+            !event.method.starts_with("Noise.")
+        }
+    }
+
+    /// Forwards the connection status updates it receives to a channel.
+    struct Statuses(tokio::sync::mpsc::UnboundedSender<ConnectionStatusUpdate>);
+
+    impl Actor for Statuses { type Context = Context<Self>; }
+
+    impl Handler<ConnectionStatusUpdate> for Statuses {
+        type Result = ();
+        fn handle(&mut self, update: ConnectionStatusUpdate, _ctx: &mut Context<Self>) {
+            let _ = self.0.send(update);
+        }
+    }
+
+    fn statuses() -> (Recipient<ConnectionStatusUpdate>, tokio::sync::mpsc::UnboundedReceiver<ConnectionStatusUpdate>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (Statuses(tx).start().recipient(), rx)
+    }
+
+    /// Skips updates until one in a state matching `expected`.
+    async fn wait_for_state(
+        rx: &mut tokio::sync::mpsc::UnboundedReceiver<ConnectionStatusUpdate>,
+        expected: impl Fn(&ConnectionState) -> bool,
+    ) -> ConnectionStatusUpdate {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let update = rx.recv().await.unwrap();
+                if expected(&update.state) {
+                    return update;
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Accepts one WebSocket client on a random port and hands its socket to `serve`.
+    async fn raw_server<F, Fut>(serve: F) -> String
+    where
+        F: FnOnce(tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/devtools/browser/raw", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            serve(tokio_tungstenite::accept_async(socket).await.unwrap()).await;
+        });
+        url
+    }
+
+    fn config_from_toml(toml: &str) -> config::Config {
+        ::config::Config::builder()
+            .add_source(::config::File::from_str(toml, ::config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[actix::test]
+    #[allow(clippy::result_large_err)] // The callback signature is tungstenite's
+    async fn test_launch_connection_merges_browser_headers_and_query_params() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (handshake_tx, handshake_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_hdr_async(socket, |request: &Request, response: Response| {
+                let _ = handshake_tx.send((request.uri().to_string(), request.headers().clone()));
+                Ok(response)
+            })
+            .await
+            .unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let config = config_from_toml(
+            r#"
+            [browsers.grid.headers]
+            Authorization = "Bearer from-config"
+            X-Grid-Region = "eu"
+
+            [browsers.grid.query_params]
+            token = "abc"
+            "#,
+        );
+        let supervisor = SupervisorActor::new(Some(config)).start();
+        let params = ConnectParams {
+            headers: [("authorization".to_string(), "Bearer from-request".to_string())].into(),
+            ..ConnectParams::new(format!("ws://127.0.0.1:{}/devtools/browser/test", port))
+        };
+        supervisor
+            .send(LaunchConnection { params, browser: Some("grid".to_string()), owner: None })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (uri, headers) = tokio::time::timeout(Duration::from_secs(5), handshake_rx).await.unwrap().unwrap();
+        assert!(uri.ends_with("/devtools/browser/test?token=abc"), "{}", uri);
+        // The request's own header wins over the configured one, whatever its case
+        let authorization: Vec<_> = headers.get_all("authorization").iter().collect();
+        assert_eq!(authorization, ["Bearer from-request"]);
+        assert_eq!(headers["x-grid-region"], "eu");
+    }
+
+    #[actix::test]
+    async fn test_commands_time_out_and_leave_the_pending_table() {
+        let server = MockCdpServer::start().await.unwrap();
+        server.on("Slow.op", |_| Reply::silence());
+        server.fail_next("Browser.getVersion", -32000, "nope");
+        let config = config_from_toml("[global]\ndefault_command_timeout_ms = 300");
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config))).await;
+        settled(&route.commands).await;
+
+        let started = Instant::now();
+        let slow = route.commands.send(command("Slow.op"));
+        assert_eq!(route.commands.send(GetPendingCount).await.unwrap(), 1);
+        // Other commands are answered while one is outstanding
+        let failed = route.commands.send(command("Browser.getVersion")).await.unwrap();
+        assert!(matches!(failed, Err(ProtocolError::BrowserError { code: -32000, .. })), "{:?}", failed);
+        let version = route.commands.send(command("Browser.getVersion")).await.unwrap().unwrap();
+        assert_eq!(version["product"], "MockChrome/1.0");
+        assert_eq!(route.commands.send(GetPendingCount).await.unwrap(), 1);
+
+        let result = slow.await.unwrap();
+        assert!(matches!(result, Err(ProtocolError::Timeout)), "{:?}", result);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(route.commands.send(GetPendingCount).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_event_filter_matches() {
+        let event = |method: &str, session_id: Option<&str>| ProtocolEvent {
+            session_id: session_id.map(String::from),
+            method: method.to_string(),
+            params: serde_json::Value::Null,
+        };
+        let exact = EventFilter::method("Page.loadEventFired");
+        assert!(exact.matches(&event("Page.loadEventFired", None)));
+        assert!(exact.matches(&event("Page.loadEventFired", Some("S1"))));
+        assert!(!exact.matches(&event("Page.frameNavigated", None)));
+
+        let domain = EventFilter::method("Page.*");
+        assert!(domain.matches(&event("Page.frameNavigated", None)));
+        assert!(!domain.matches(&event("PageX.frameNavigated", None)));
+        assert!(!domain.matches(&event("Page", None)));
+
+        assert!(EventFilter::method("*").matches(&event("Runtime.consoleAPICalled", None)));
+        let in_session = EventFilter::method("*").in_session("S1");
+        assert!(in_session.matches(&event("Runtime.consoleAPICalled", Some("S1"))));
+        assert!(!in_session.matches(&event("Runtime.consoleAPICalled", Some("S2"))));
+        assert!(!in_session.matches(&event("Runtime.consoleAPICalled", None)));
+    }
+
+    #[actix::test]
+    async fn test_subscriptions_get_matching_events_until_removed_or_stopped() {
+        let server = MockCdpServer::start().await.unwrap();
+        let (_supervisor, route) = launch(&server).await;
+        let (page, mut page_events) = forward();
+        let (all, mut all_events) = forward();
+        let page_id = route.events
+            .send(Subscribe { filter: EventFilter::method("Page.*").in_session("S1"), recipient: page })
+            .await
+            .unwrap();
+        route.events.send(Subscribe { filter: EventFilter::method("*"), recipient: all }).await.unwrap();
+        // A subscriber whose actor is gone
+        let stopped = Context::<Forward>::new().address().recipient();
+        let stopped_id = route.events.send(Subscribe { filter: EventFilter::method("*"), recipient: stopped }).await.unwrap();
+
+        server.emit("Page.loadEventFired", serde_json::json!({ "timestamp": 1 }), Some("S2"));
+        server.emit("Page.loadEventFired", serde_json::json!({ "timestamp": 2 }), Some("S1"));
+        let event = next_event(&mut page_events).await;
+        assert_eq!((event.session_id.as_deref(), &event.params["timestamp"]), (Some("S1"), &serde_json::json!(2)));
+        assert_eq!(next_event(&mut all_events).await.params["timestamp"], 1);
+        assert_eq!(next_event(&mut all_events).await.params["timestamp"], 2);
+        // Pruned on the first event that reached it
+        assert!(!route.events.send(Unsubscribe(stopped_id)).await.unwrap());
+
+        assert!(route.events.send(Unsubscribe(page_id)).await.unwrap());
+        assert!(!route.events.send(Unsubscribe(page_id)).await.unwrap());
+        server.emit("Page.loadEventFired", serde_json::json!({ "timestamp": 3 }), Some("S1"));
+        assert_eq!(next_event(&mut all_events).await.params["timestamp"], 3);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(page_events.try_recv().is_err());
+    }
+
+    #[actix::test]
+    async fn test_shutdown_drains_commands_before_closing_the_browser_and_connection() {
+        let server = MockCdpServer::start().await.unwrap();
+        server.on("Slow.op", |_| Reply::silence());
+        let (supervisor, route) = launch(&server).await;
+        settled(&route.commands).await;
+
+        let slow = route.commands.send(command("Slow.op"));
+        let shutdown = supervisor.send(Shutdown { drain_timeout: Duration::from_secs(5), close_browsers: true });
+        let shutdown = actix::spawn(shutdown);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let rejected = route.commands.send(command("Browser.getVersion")).await.unwrap();
+        assert!(matches!(rejected, Err(ProtocolError::ShuttingDown)), "{:?}", rejected);
+        // Still draining: the browser is only closed once the command in flight is answered
+        let received = server.received();
+        assert!(!received.iter().any(|r| r.method == "Browser.close"));
+        let id = received.iter().find(|r| r.method == "Slow.op").unwrap().id;
+        route.commands.do_send(IncomingRawMessage { connection_id: route.connection.id, raw: format!(r#"{{"id":{},"result":{{"done":true}}}}"#, id) });
+        assert_eq!(slow.await.unwrap().unwrap()["done"], true);
+
+        tokio::time::timeout(Duration::from_secs(3), shutdown).await.unwrap().unwrap().unwrap();
+        assert_eq!(server.received().last().unwrap().method, "Browser.close");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(server.connection_count(), 0);
+        assert!(!supervisor.connected());
+    }
+
+    #[actix::test]
+    async fn test_shutdown_fails_commands_still_pending_at_the_drain_deadline() {
+        let server = MockCdpServer::start().await.unwrap();
+        server.on("Slow.op", |_| Reply::silence());
+        let (supervisor, route) = launch(&server).await;
+        settled(&route.commands).await;
+
+        let slow = route.commands.send(command("Slow.op"));
+        let started = Instant::now();
+        supervisor.send(Shutdown { drain_timeout: Duration::from_millis(300), close_browsers: false }).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        let result = slow.await.unwrap();
+        assert!(matches!(result, Err(ProtocolError::ConnectionLost(_))), "{:?}", result);
+        assert!(!server.received().iter().any(|r| r.method == "Browser.close"));
+        // The supervisor stops once the shutdown completes
+        assert!(supervisor.send(ListConnections).await.is_err());
+    }
+
+    #[actix::test]
+    async fn test_plugins_run_in_configured_order_unless_disabled() {
+        let server = MockCdpServer::start().await.unwrap();
+        let config = config_from_toml(
+            r#"
+            [plugins.late]
+            order = 10
+
+            [plugins.off]
+            enabled = false
+            "#,
+        );
+        let registry = PluginRegistry::new().register(Stamp("late")).register(Stamp("off")).register(Stamp("early"));
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config)).with_plugins(registry)).await;
+        settled(&route.commands).await;
+
+        route.commands.send(command("Browser.getVersion")).await.unwrap().unwrap();
+        let sent = server.received().into_iter().find(|r| r.method == "Browser.getVersion").unwrap();
+        assert_eq!(sent.params["plugins"], serde_json::json!(["early", "late"]));
+        // The first plugin in order owns the domain; the browser never sees the command
+        let answer = route.commands.send(command("Janus.whoami")).await.unwrap().unwrap();
+        assert_eq!(answer["answeredBy"], "early");
+        assert!(!server.received().iter().any(|r| r.method == "Janus.whoami"));
+
+        let (all, mut events) = forward();
+        route.events.send(Subscribe { filter: EventFilter::method("*"), recipient: all }).await.unwrap();
+        server.emit("Noise.tick", serde_json::json!({}), None);
+        server.emit("Page.loadEventFired", serde_json::json!({}), None);
+        assert_eq!(next_event(&mut events).await.method, "Page.loadEventFired");
+    }
+
+    #[actix::test]
+    async fn test_idempotent_commands_are_retried_with_backoff() {
+        let server = MockCdpServer::start().await.unwrap();
+        let config = config_from_toml(
+            r#"
+            [global.command_retry]
+            initial_backoff_ms = 50
+            backoff_multiplier = 2.0
+            retry_on_error_codes = [-32000]
+            "#,
+        );
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config))).await;
+        settled(&route.commands).await;
+        let attempts = |method: &str| server.received().iter().filter(|r| r.method == method).count();
+        let before = attempts("Browser.getVersion");
+
+        // Fails twice, then succeeds after 50 + 100ms of backoff
+        server.fail_next("Browser.getVersion", -32000, "busy");
+        server.fail_next("Browser.getVersion", -32000, "busy");
+        let started = Instant::now();
+        let version = route.commands.send(ExecuteCommand { idempotent: true, ..command("Browser.getVersion") }).await.unwrap().unwrap();
+        assert_eq!(version["product"], "MockChrome/1.0");
+        assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+        assert_eq!(attempts("Browser.getVersion") - before, 3);
+
+        // Gives up after max_attempts
+        for _ in 0..3 {
+            server.fail_next("Target.getTargets", -32000, "busy");
+        }
+        let result = route.commands.send(command("Target.getTargets")).await.unwrap();
+        assert!(matches!(result, Err(ProtocolError::BrowserError { code: -32000, .. })), "{:?}", result);
+        assert_eq!(attempts("Target.getTargets"), 3);
+
+        // Neither commands that are not idempotent nor other errors are retried
+        server.fail_next("Page.navigate", -32000, "busy");
+        assert!(route.commands.send(command("Page.navigate")).await.unwrap().is_err());
+        assert_eq!(attempts("Page.navigate"), 1);
+        server.fail_next("Target.getTargets", -32602, "invalid");
+        assert!(route.commands.send(command("Target.getTargets")).await.unwrap().is_err());
+        assert_eq!(attempts("Target.getTargets"), 4);
+    }
+
+    #[actix::test]
+    async fn test_commands_over_the_rate_limit_are_queued_in_order() {
+        let server = MockCdpServer::start().await.unwrap();
+        let config = config_from_toml(
+            r#"
+            [browsers.test.rate_limit.commands]
+            per_second = 20.0
+            burst = 1
+            "#,
+        );
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config))).await;
+        settled(&route.commands).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        let responses: Vec<_> = (0..4)
+            .map(|n| route.commands.send(ExecuteCommand { params: serde_json::json!({ "n": n }), ..command("Browser.getVersion") }))
+            .collect();
+        // One went out at once, the rest wait their turn
+        assert_eq!(route.commands.send(GetPendingCount).await.unwrap(), 4);
+        for response in join_all(responses).await {
+            response.unwrap().unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+        let order: Vec<_> = server.received().into_iter()
+            .filter(|r| r.method == "Browser.getVersion")
+            .map(|r| r.params["n"].clone())
+            .collect();
+        assert_eq!(order, [0, 1, 2, 3]);
+        assert_eq!(route.commands.send(GetPendingCount).await.unwrap(), 0);
+    }
+
+    #[actix::test]
+    async fn test_connections_are_listed_and_report_status_to_subscribers() {
+        let server = MockCdpServer::start().await.unwrap();
+        server.on("Slow.op", |_| Reply::silence());
+        let supervisor = SupervisorActor::new(None).start();
+        let (all, mut all_updates) = statuses();
+        supervisor.send(SubscribeConnectionStatus { connection: None, recipient: all }).await.unwrap();
+        let (removed, mut removed_updates) = statuses();
+        let removed = supervisor.send(SubscribeConnectionStatus { connection: None, recipient: removed }).await.unwrap();
+        assert!(supervisor.send(UnsubscribeConnectionStatus(removed)).await.unwrap());
+        assert!(!supervisor.send(UnsubscribeConnectionStatus(removed)).await.unwrap());
+        let (owner, mut owner_updates) = statuses();
+        let first = supervisor
+            .send(LaunchConnection { params: ConnectParams::new(server.ws_url()), browser: None, owner: Some(owner) })
+            .await
+            .unwrap()
+            .unwrap();
+        let second = supervisor
+            .send(LaunchConnection { params: ConnectParams::new(server.ws_url()), browser: None, owner: None })
+            .await
+            .unwrap()
+            .unwrap();
+        let (of_second, mut second_updates) = statuses();
+        supervisor.send(SubscribeConnectionStatus { connection: Some(second), recipient: of_second }).await.unwrap();
+
+        // The owner sees every update, starting with the first
+        assert!(matches!(owner_updates.recv().await.unwrap().state, ConnectionState::Connecting));
+        wait_for_state(&mut owner_updates, |state| matches!(state, ConnectionState::Connected)).await;
+        let update = wait_for_state(&mut second_updates, |state| matches!(state, ConnectionState::Connected)).await;
+        assert_eq!(update.id, second);
+
+        let route = supervisor.send(GetConnectionRoute(first)).await.unwrap().unwrap();
+        settled(&route.commands).await;
+        let _slow = route.commands.send(command("Slow.op"));
+        let connections = supervisor.send(ListConnections).await.unwrap();
+        assert_eq!(connections.iter().map(|c| c.id).collect::<Vec<_>>(), [first, second]);
+        assert!(connections.iter().all(|c| c.url == server.ws_url() && matches!(c.state, ConnectionState::Connected)));
+        let info = supervisor.send(GetConnection(first)).await.unwrap().unwrap();
+        assert_eq!(info.pending_commands, 1);
+        assert!(info.last_error.is_none());
+        assert!(supervisor.send(GetConnection(second + 1)).await.unwrap().is_none());
+
+        server.disconnect_all();
+        wait_for_state(&mut owner_updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+        let update = wait_for_state(&mut second_updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+        assert_eq!(update.id, second);
+        let mut disconnected = Vec::new();
+        while disconnected.len() < 2 {
+            let update = wait_for_state(&mut all_updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+            disconnected.push(update.id);
+        }
+        disconnected.sort_unstable();
+        assert_eq!(disconnected, [first, second]);
+        assert!(supervisor.send(ListConnections).await.unwrap().is_empty());
+        assert!(removed_updates.try_recv().is_err());
+    }
+
+    #[actix::test]
+    async fn test_configured_keepalive_pings_and_drops_silent_peer() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let (pings_tx, mut pings) = tokio::sync::mpsc::unbounded_channel();
+        let url = raw_server(|mut socket| async move {
+            // Pongs go out while reading, so once reading stops the next ping goes unanswered
+            while let Some(Ok(frame)) = socket.next().await {
+                if matches!(frame, Message::Ping(_)) && pings_tx.send(()).is_err() {
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        })
+        .await;
+        let config = config_from_toml("[transport.websocket]\nping_interval_ms = 50\npong_timeout_ms = 200\n");
+        let supervisor = SupervisorActor::new(Some(config)).start();
+        let (owner, mut updates) = statuses();
+        let params = ConnectParams::new(url);
+        supervisor.send(LaunchConnection { params, browser: None, owner: Some(owner) }).await.unwrap().unwrap();
+
+        wait_for_state(&mut updates, |state| *state == ConnectionState::Connected).await;
+        for _ in 0..3 {
+            tokio::time::timeout(Duration::from_secs(2), pings.recv()).await.expect("no keepalive ping").unwrap();
+        }
+        drop(pings);
+
+        let update = wait_for_state(&mut updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+        assert!(matches!(update.state, ConnectionState::Disconnected(Some(TransportError::Timeout(_)))), "{:?}", update.state);
+    }
+
+    #[actix::test]
+    async fn test_configured_ca_bundle_connects_to_wss_server() {
+        let server = MockCdpServer::start_tls().await.unwrap();
+        assert!(server.ws_url().starts_with("wss://"));
+
+        // Without the extra root the server's certificate is rejected
+        let supervisor = SupervisorActor::new(Some(config_from_toml(""))).start();
+        let (owner, mut updates) = statuses();
+        let params = ConnectParams::new(server.ws_url());
+        supervisor.send(LaunchConnection { params, browser: None, owner: Some(owner) }).await.unwrap().unwrap();
+        let update = wait_for_state(&mut updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+        assert!(matches!(update.state, ConnectionState::Disconnected(Some(TransportError::TlsError(_)))), "{:?}", update.state);
+
+        let config = config_from_toml(&format!("[transport.tls]\nca_bundle_path = {:?}\n", mock_cdp::TEST_CA_BUNDLE));
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config))).await;
+        let version = route.commands.send(command("Browser.getVersion")).await.unwrap().unwrap();
+        assert_eq!(version["product"], "MockChrome/1.0");
+    }
+
+    #[actix::test]
+    async fn test_configured_connect_timeout_applies() {
+        // Accepts the TCP connection but never answers the WebSocket handshake
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/devtools/browser/mute", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _socket = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+
+        let config = config_from_toml("[transport]\nconnect_timeout_ms = 200\n");
+        let supervisor = SupervisorActor::new(Some(config)).start();
+        let (owner, mut updates) = statuses();
+        let params = ConnectParams::new(url);
+        let started = Instant::now();
+        supervisor.send(LaunchConnection { params, browser: None, owner: Some(owner) }).await.unwrap().unwrap();
+        let update = wait_for_state(&mut updates, |state| matches!(state, ConnectionState::Disconnected(_))).await;
+        assert!(matches!(update.state, ConnectionState::Disconnected(Some(TransportError::Timeout(_)))), "{:?}", update.state);
+        assert!(started.elapsed() < Duration::from_secs(2), "timed out after {:?}", started.elapsed());
+    }
+
+    #[actix::test]
+    async fn test_failed_launch_is_not_registered() {
+        let supervisor = SupervisorActor::new(None).start();
+        let params = ConnectParams::new("bogus://127.0.0.1:1");
+        let result = supervisor.send(LaunchConnection { params, browser: None, owner: None }).await.unwrap();
+        assert!(matches!(result, Err(CoreError::Transport(TransportError::UnsupportedScheme(_)))), "{:?}", result);
+        assert!(supervisor.send(ListConnections).await.unwrap().is_empty());
+    }
+
+    #[actix::test]
+    async fn test_configured_recording_captures_traffic() {
+        let server = MockCdpServer::start().await.unwrap();
+        let dir = std::env::temp_dir().join(format!("janus-recording-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("traffic.jsonl");
+        let config = config_from_toml(&format!("[transport.recording]\npath = {:?}\n", path.to_str().unwrap()));
+
+        let (_supervisor, route) = launch_with(&server, SupervisorActor::new(Some(config))).await;
+        route.commands.send(command("Browser.getVersion")).await.unwrap().unwrap();
+        // Lines are written by the recorder's thread, which flushes after each batch
+        let recorded = |direction: &str, check: &dyn Fn(&serde_json::Value) -> bool| {
+            std::fs::read_to_string(&path).unwrap().lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .any(|line| line["direction"] == direction && check(&line["frame"]))
+        };
+        for _ in 0..100 {
+            if recorded("in", &|frame| frame["result"]["product"] == "MockChrome/1.0") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(recorded("out", &|frame| frame["method"] == "Browser.getVersion"));
+        assert!(recorded("in", &|frame| frame["result"]["product"] == "MockChrome/1.0"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix::test]
+    async fn test_responses_resolve_only_their_own_connections_commands() {
+        let (first, second) = (MockCdpServer::start().await.unwrap(), MockCdpServer::start().await.unwrap());
+        first.on("Test.whoami", |_| Reply::result(serde_json::json!({ "server": "first" })));
+        second.on("Test.whoami", |_| Reply::result(serde_json::json!({ "server": "second" })));
+        let (supervisor, first_route) = launch(&first).await;
+        let second_route = launch_on(&supervisor, &second, None).await;
+        settled(&first_route.commands).await;
+        settled(&second_route.commands).await;
+
+        let (from_first, from_second) = tokio::join!(
+            first_route.commands.send(command("Test.whoami")),
+            second_route.commands.send(command("Test.whoami")),
+        );
+        assert_eq!(from_first.unwrap().unwrap()["server"], "first");
+        assert_eq!(from_second.unwrap().unwrap()["server"], "second");
+        // Both connections numbered their commands alike, so the ids alone could not tell them apart
+        let id_of = |server: &MockCdpServer| server.received().iter().find(|r| r.method == "Test.whoami").unwrap().id;
+        assert_eq!(id_of(&first), id_of(&second));
+    }
+
+    /// Accepts and drops every frame.
+    struct Discard;
+
+    impl Actor for Discard { type Context = Context<Self>; }
+
+    impl Handler<SendRawMessage> for Discard {
+        type Result = Result<(), TransportError>;
+        fn handle(&mut self, _msg: SendRawMessage, _ctx: &mut Context<Self>) -> Self::Result {
+            Ok(())
+        }
+    }
+
+    #[actix::test]
+    async fn test_command_ids_wrap_below_replay_ids_and_skip_pending_ones() {
+        let mut commands = CommandActor::new(1, Discard.start().recipient(), Duration::from_secs(1));
+        let (responder, _response) = oneshot::channel();
+        commands.pending.insert(1, PendingCommand { method: "Slow.op".to_string(), responder, timeout: SpawnHandle::default() });
+        commands.next_id = REPLAY_ID_BASE - 1;
+
+        assert_eq!(commands.allocate_id(), REPLAY_ID_BASE - 1);
+        // Wraps before the replay range, past the id still waiting for its response
+        assert_eq!(commands.allocate_id(), 2);
+        assert_eq!(commands.allocate_id(), 3);
+    }
+}
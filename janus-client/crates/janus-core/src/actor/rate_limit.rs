@@ -0,0 +1,143 @@
+//! Token buckets pacing the commands a `CommandActor` sends to its browser.
+
+use crate::config::{RateLimitConfig, TokenBucketConfig};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// `None` for a bucket that could never refill.
+    fn new(name: &str, config: &TokenBucketConfig) -> Option<Self> {
+        if config.per_second.is_nan() || config.per_second <= 0.0 {
+            log::warn!("Ignoring the {} rate limit: per_second must be positive, got {}", name, config.per_second);
+            return None;
+        }
+        let capacity = f64::from(config.burst.max(1));
+        Some(Self { capacity, per_second: config.per_second, tokens: capacity, refilled: Instant::now() })
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled = now;
+    }
+
+    /// How long until a token is available; zero if one is.
+    fn wait(&self) -> Duration {
+        Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / self.per_second)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    commands: Option<TokenBucket>,
+    heavy: Option<TokenBucket>,
+    heavy_methods: Vec<String>,
+}
+
+impl From<&RateLimitConfig> for RateLimiter {
+    fn from(config: &RateLimitConfig) -> Self {
+        Self {
+            commands: config.commands.as_ref().and_then(|bucket| TokenBucket::new("command", bucket)),
+            heavy: config.heavy.as_ref().and_then(|bucket| TokenBucket::new("heavy operation", bucket)),
+            heavy_methods: config.heavy_methods.clone(),
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Takes a token for `method` from every bucket it draws on, or none at all and
+    /// returns how long to wait before trying again.
+    pub(crate) fn try_acquire(&mut self, method: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let heavy = self.heavy_methods.iter().any(|heavy| heavy == method);
+        let mut buckets: Vec<&mut TokenBucket> = self.commands.iter_mut().collect();
+        if heavy {
+            buckets.extend(self.heavy.iter_mut());
+        }
+
+        for bucket in buckets.iter_mut() {
+            bucket.refill(now);
+        }
+        let wait = buckets.iter().map(|bucket| bucket.wait()).max().unwrap_or_default();
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for bucket in buckets {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(commands: Option<(f64, u32)>, heavy: Option<(f64, u32)>) -> RateLimiter {
+        let bucket = |(per_second, burst)| TokenBucketConfig { per_second, burst };
+        RateLimiter::from(&RateLimitConfig {
+            commands: commands.map(bucket),
+            heavy: heavy.map(bucket),
+            ..Default::default()
+        })
+    }
+
+    /// Moves the buckets' clock `elapsed` into the past, as if that much time had passed.
+    fn elapse(limiter: &mut RateLimiter, elapsed: Duration) {
+        for bucket in limiter.commands.iter_mut().chain(limiter.heavy.iter_mut()) {
+            bucket.refilled -= elapsed;
+        }
+    }
+
+    #[test]
+    fn test_bucket_allows_a_burst_then_refills() {
+        let mut limiter = limiter(Some((10.0, 2)), None);
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        let wait = limiter.try_acquire("Page.navigate").unwrap_err();
+        assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100), "{:?}", wait);
+
+        elapse(&mut limiter, Duration::from_millis(100));
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_err());
+        // Never more than the burst, however long it was quiet
+        elapse(&mut limiter, Duration::from_secs(60));
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_err());
+    }
+
+    #[test]
+    fn test_heavy_methods_draw_on_both_buckets() {
+        let mut limiter = limiter(Some((100.0, 3)), Some((1.0, 1)));
+        assert!(limiter.try_acquire("Page.captureScreenshot").is_ok());
+        let wait = limiter.try_acquire("Page.captureScreenshot").unwrap_err();
+        assert!(wait > Duration::from_millis(900), "{:?}", wait);
+        // The rejected heavy command took no token from the command bucket either
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_ok());
+        assert!(limiter.try_acquire("Page.navigate").is_err());
+    }
+
+    #[test]
+    fn test_missing_or_invalid_buckets_do_not_limit() {
+        let mut unlimited = RateLimiter::default();
+        let mut invalid = limiter(Some((0.0, 1)), Some((f64::NAN, 1)));
+        for _ in 0..100 {
+            assert!(unlimited.try_acquire("Page.captureScreenshot").is_ok());
+            assert!(invalid.try_acquire("Page.captureScreenshot").is_ok());
+        }
+        // A burst of 0 still lets one command through
+        let mut no_burst = limiter(Some((1.0, 0)), None);
+        assert!(no_burst.try_acquire("Page.navigate").is_ok());
+        assert!(no_burst.try_acquire("Page.navigate").is_err());
+    }
+}
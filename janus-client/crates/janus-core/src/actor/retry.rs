@@ -0,0 +1,113 @@
+//! Which failed commands the `CommandActor` sends again, and when.
+
+use super::ExecuteCommand;
+use crate::config::RetryConfig;
+use crate::error::{ProtocolError, TransportError};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    config: RetryConfig,
+}
+
+impl From<RetryConfig> for RetryPolicy {
+    fn from(config: RetryConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.config.max_attempts.max(1)
+    }
+
+    /// Whether `command` is safe to send more than once.
+    pub(crate) fn is_retryable(&self, command: &ExecuteCommand) -> bool {
+        if self.max_attempts() <= 1 {
+            return false;
+        }
+        if self.config.idempotent_methods.contains(&command.method) {
+            return true;
+        }
+        // A remote object result would leave an object behind for every attempt
+        command.idempotent && (command.method != "Runtime.evaluate" || command.params["returnByValue"] == true)
+    }
+
+    /// Whether `error` is transient, i.e. the same command may succeed when sent again.
+    pub(crate) fn is_transient(&self, error: &ProtocolError) -> bool {
+        match error {
+            // `NotConnected` while the connection is being re-established
+            ProtocolError::SendFailed(TransportError::SendFailed(_) | TransportError::NotConnected) => true,
+            ProtocolError::ConnectionLost(_) => true,
+            ProtocolError::BrowserError { code, .. } => self.config.retry_on_error_codes.contains(code),
+            ProtocolError::Timeout => self.config.retry_on_timeout,
+            _ => false,
+        }
+    }
+
+    /// Delay after the failed `attempt` (1-based).
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.config.backoff_multiplier.max(1.0).powi(attempt.saturating_sub(1) as i32);
+        let delay = (self.config.initial_backoff_ms as f64 * factor).min(self.config.max_backoff_ms as f64);
+        Duration::from_millis(delay as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn command(method: &str, params: serde_json::Value, idempotent: bool) -> ExecuteCommand {
+        ExecuteCommand { session_id: None, method: method.to_string(), params, idempotent }
+    }
+
+    #[test]
+    fn test_only_idempotent_commands_are_retryable() {
+        let policy = RetryPolicy::from(RetryConfig::default());
+        assert!(policy.is_retryable(&command("Target.getTargets", json!({}), false)));
+        assert!(!policy.is_retryable(&command("Page.navigate", json!({}), false)));
+        assert!(policy.is_retryable(&command("Page.navigate", json!({}), true)));
+        // Only by value, as a remote object would be left behind for every attempt
+        assert!(policy.is_retryable(&command("Runtime.evaluate", json!({ "returnByValue": true }), true)));
+        assert!(!policy.is_retryable(&command("Runtime.evaluate", json!({ "returnByValue": false }), true)));
+        assert!(!policy.is_retryable(&command("Runtime.evaluate", json!({}), true)));
+        assert!(!policy.is_retryable(&command("Runtime.evaluate", json!({ "returnByValue": true }), false)));
+
+        let disabled = RetryPolicy::from(RetryConfig { max_attempts: 1, ..Default::default() });
+        assert!(!disabled.is_retryable(&command("Target.getTargets", json!({}), true)));
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let policy = RetryPolicy::from(RetryConfig { retry_on_error_codes: vec![-32000], ..Default::default() });
+        assert!(policy.is_transient(&ProtocolError::SendFailed(TransportError::SendFailed("dropped".to_string()))));
+        assert!(policy.is_transient(&ProtocolError::SendFailed(TransportError::NotConnected)));
+        assert!(!policy.is_transient(&ProtocolError::SendFailed(TransportError::InvalidUrl("x".to_string()))));
+        assert!(policy.is_transient(&ProtocolError::ConnectionLost("reconnecting".to_string())));
+        let browser_error = |code| ProtocolError::BrowserError { code, message: "error".to_string(), data: None };
+        assert!(policy.is_transient(&browser_error(-32000)));
+        assert!(!policy.is_transient(&browser_error(-32601)));
+        assert!(!policy.is_transient(&ProtocolError::Timeout));
+        assert!(!policy.is_transient(&ProtocolError::InvalidRequest("bad".to_string())));
+
+        let on_timeout = RetryPolicy::from(RetryConfig { retry_on_timeout: true, ..Default::default() });
+        assert!(on_timeout.is_transient(&ProtocolError::Timeout));
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::from(RetryConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            backoff_multiplier: 3.0,
+            ..Default::default()
+        });
+        let delays: Vec<u64> = (1..=4).map(|attempt| policy.backoff(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, [100, 300, 900, 1_000]);
+
+        // A multiplier below 1 never shrinks the delay
+        let constant = RetryPolicy::from(RetryConfig { backoff_multiplier: 0.5, ..Default::default() });
+        assert_eq!(constant.backoff(3), constant.backoff(1));
+    }
+}
@@ -0,0 +1,584 @@
+//! One attached target (page, iframe, worker) of a browser.
+//!
+//! A `SessionActor` runs every command for its target through the connection's
+//! `CommandActor` with the target's session id, and keeps the state the rest of the
+//! client would otherwise have to re-query: enabled domains, the frame tree and the
+//! JavaScript execution contexts.
+//!
+//! Session actors are started under an actix `Supervisor`, which restarts an actor in
+//! place (same address) when it stops, but not when it panics: a panic takes down the
+//! arbiter and every actor on it. So when events leave the state inconsistent the actor
+//! stops itself, and resyncs from the browser on restart; other sessions of the browser
+//! are unaffected. Frame events received while the frame tree is loading are held back
+//! and applied on top of it, as they may race the `Page.getFrameTree` response.
+
+use super::{CommandActor, ExecuteCommand, ProtocolEvent};
+use crate::error::ProtocolError;
+use actix::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// A frame of the target's frame tree.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameInfo {
+    pub id: String,
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub url: String,
+    pub name: Option<String>,
+}
+
+/// A JavaScript execution context, as reported by `Runtime.executionContextCreated`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionContext {
+    pub id: i64,
+    pub origin: String,
+    pub frame_id: Option<String>,
+    /// The page's own context of the frame, as opposed to one created by an extension or `Page.createIsolatedWorld`.
+    pub is_default: bool,
+}
+
+/// Point-in-time copy of a session's state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionState {
+    pub target_id: String,
+    pub session_id: String,
+    pub target_type: String,
+    pub enabled_domains: BTreeSet<String>,
+    pub main_frame: Option<FrameInfo>,
+    pub frames: Vec<FrameInfo>,
+    pub execution_contexts: Vec<ExecutionContext>,
+    /// The target went away; every command now fails with `TargetOrSessionNotFound`.
+    pub detached: bool,
+}
+
+impl SessionState {
+    /// The default execution context of the main frame, if it has been created yet.
+    pub fn main_context(&self) -> Option<&ExecutionContext> {
+        let main_frame = self.main_frame.as_ref()?;
+        self.execution_contexts.iter()
+            .find(|context| context.is_default && context.frame_id.as_deref() == Some(main_frame.id.as_str()))
+    }
+}
+
+// --- Messages ---
+
+/// Runs a command on this session's target. `X.enable`/`X.disable` are tracked, so the
+/// domain is enabled again after a restart.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Result<serde_json::Value, ProtocolError>")]
+pub struct SessionCommand {
+    pub method: String,
+    pub params: serde_json::Value,
+    /// See `ExecuteCommand::idempotent`.
+    pub idempotent: bool,
+}
+
+impl SessionCommand {
+    pub fn new(method: impl Into<String>, params: serde_json::Value) -> Self {
+        Self { method: method.into(), params, idempotent: false }
+    }
+
+    /// Marks the command as safe to retry.
+    pub fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
+}
+
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "SessionState")]
+pub struct GetSessionState;
+
+/// Closes the target (`Target.closeTarget`). The browser then reports it detached.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "Result<(), ProtocolError>")]
+pub struct CloseTarget;
+
+/// Sent by the `BrowserActor` when the target is detached or destroyed.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "()")]
+pub struct TargetDetached;
+
+// --- Actor ---
+
+#[derive(Debug)]
+pub struct SessionActor {
+    target_id: String,
+    session_id: String,
+    target_type: String,
+    commands: Addr<CommandActor>,
+    // Survives restarts, so domains are enabled again on resync
+    enabled_domains: BTreeSet<String>,
+    main_frame_id: Option<String>,
+    frames: HashMap<String, FrameInfo>,
+    execution_contexts: HashMap<i64, ExecutionContext>,
+    /// `Page.*` events received while `Page.getFrameTree` is in flight; `None` once the tree is loaded.
+    held_frame_events: Option<Vec<ProtocolEvent>>,
+    detached: bool,
+}
+
+impl SessionActor {
+    pub fn new(target_id: String, session_id: String, target_type: String, commands: Addr<CommandActor>) -> Self {
+        // Frame tree and execution contexts are maintained from these domains' events
+        let enabled_domains = match target_type.as_str() {
+            "page" | "iframe" => ["Page", "Runtime"].into_iter().map(String::from).collect(),
+            _ => ["Runtime"].into_iter().map(String::from).collect(),
+        };
+        Self {
+            target_id,
+            session_id,
+            target_type,
+            commands,
+            enabled_domains,
+            main_frame_id: None,
+            frames: HashMap::new(),
+            execution_contexts: HashMap::new(),
+            held_frame_events: None,
+            detached: false,
+        }
+    }
+
+    fn execute(&self, method: &str, params: serde_json::Value) -> Request<CommandActor, ExecuteCommand> {
+        self.execute_command(method, params, false)
+    }
+
+    fn execute_command(&self, method: &str, params: serde_json::Value, idempotent: bool) -> Request<CommandActor, ExecuteCommand> {
+        self.commands.send(ExecuteCommand { session_id: Some(self.session_id.clone()), method: method.to_string(), params, idempotent })
+    }
+
+    /// Enables the tracked domains and reloads the frame tree from the browser.
+    fn sync(&mut self, ctx: &mut Context<Self>) {
+        let enables: Vec<_> = self.enabled_domains.iter()
+            .map(|domain| (domain.clone(), self.execute(&format!("{}.enable", domain), serde_json::json!({}))))
+            .collect();
+        let has_frames = self.enabled_domains.contains("Page");
+        let frame_tree = has_frames.then(|| self.execute("Page.getFrameTree", serde_json::json!({})));
+        self.held_frame_events = has_frames.then(Vec::new);
+        let session_id = self.session_id.clone();
+
+        ctx.spawn(
+            async move {
+                for (domain, enable) in enables {
+                    match enable.await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(e)) => log::warn!("Session {}: enabling {} failed: {}", session_id, domain, e),
+                        Err(e) => log::warn!("Session {}: enabling {} failed: {}", session_id, domain, e),
+                    }
+                }
+                match frame_tree {
+                    Some(frame_tree) => match frame_tree.await {
+                        Ok(Ok(result)) => Some(result),
+                        Ok(Err(e)) => { log::warn!("Session {}: Page.getFrameTree failed: {}", session_id, e); None }
+                        Err(e) => { log::warn!("Session {}: Page.getFrameTree failed: {}", session_id, e); None }
+                    },
+                    None => None,
+                }
+            }
+            .into_actor(self)
+            .map(|result, act, ctx| {
+                if let Some(result) = result {
+                    if let Err(e) = act.load_frame_tree(&result["frameTree"]) {
+                        log::error!("Session {}: {}", act.session_id, e);
+                        ctx.stop(); // Restarted by the supervisor
+                        return;
+                    }
+                }
+                act.apply_held_frame_events();
+            }),
+        );
+    }
+
+    fn load_frame_tree(&mut self, tree: &serde_json::Value) -> Result<(), ProtocolError> {
+        fn walk(node: &serde_json::Value, frames: &mut Vec<FrameInfo>) -> Result<(), ProtocolError> {
+            frames.push(parse(&node["frame"])?);
+            for child in node["childFrames"].as_array().into_iter().flatten() {
+                walk(child, frames)?;
+            }
+            Ok(())
+        }
+        let mut frames = Vec::new();
+        walk(tree, &mut frames)?;
+        self.main_frame_id = frames.first().map(|frame| frame.id.clone());
+        self.frames = frames.into_iter().map(|frame| (frame.id.clone(), frame)).collect();
+        Ok(())
+    }
+
+    /// Applies the frame events held back while the tree was loading. Some may already be
+    /// reflected in the tree, so one that no longer fits is skipped rather than treated as
+    /// a desync.
+    fn apply_held_frame_events(&mut self) {
+        for event in self.held_frame_events.take().into_iter().flatten() {
+            if let Err(e) = self.apply(&event) {
+                log::debug!("Session {}: skipping {} received while loading the frame tree: {}", self.session_id, event.method, e);
+            }
+        }
+    }
+
+    /// Applies a state-changing event. An error means the state can no longer be trusted.
+    fn apply(&mut self, event: &ProtocolEvent) -> Result<(), ProtocolError> {
+        match event.method.as_str() {
+            "Page.frameNavigated" => {
+                let frame: FrameInfo = parse(&event.params["frame"])?;
+                if frame.parent_id.is_none() {
+                    // A new main document replaces the whole tree
+                    self.frames.clear();
+                    self.main_frame_id = Some(frame.id.clone());
+                }
+                self.frames.insert(frame.id.clone(), frame);
+            }
+            "Page.frameAttached" => {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct Attached { frame_id: String, parent_frame_id: String }
+                let attached: Attached = parse(&event.params)?;
+                if !self.frames.contains_key(&attached.parent_frame_id) {
+                    return Err(ProtocolError::Internal(format!("Frame {} attached to unknown parent {}", attached.frame_id, attached.parent_frame_id)));
+                }
+                self.frames.entry(attached.frame_id.clone()).or_insert(FrameInfo {
+                    id: attached.frame_id,
+                    parent_id: Some(attached.parent_frame_id),
+                    url: String::new(),
+                    name: None,
+                });
+            }
+            "Page.frameDetached" => {
+                let frame_id: String = parse(&event.params["frameId"])?;
+                // Children go with their parent
+                let mut gone = vec![frame_id];
+                while let Some(id) = gone.pop() {
+                    self.frames.remove(&id);
+                    gone.extend(self.frames.values().filter(|f| f.parent_id.as_ref() == Some(&id)).map(|f| f.id.clone()));
+                }
+            }
+            "Runtime.executionContextCreated" => {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct Created { id: i64, #[serde(default)] origin: String, aux_data: Option<AuxData> }
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct AuxData { frame_id: Option<String>, #[serde(default)] is_default: bool }
+                let created: Created = parse(&event.params["context"])?;
+                let aux = created.aux_data.unwrap_or(AuxData { frame_id: None, is_default: false });
+                self.execution_contexts.insert(created.id, ExecutionContext {
+                    id: created.id,
+                    origin: created.origin,
+                    frame_id: aux.frame_id,
+                    is_default: aux.is_default,
+                });
+            }
+            "Runtime.executionContextDestroyed" => {
+                let id: i64 = parse(&event.params["executionContextId"])?;
+                self.execution_contexts.remove(&id);
+            }
+            "Runtime.executionContextsCleared" => self.execution_contexts.clear(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> SessionState {
+        let mut frames: Vec<FrameInfo> = self.frames.values().cloned().collect();
+        frames.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut execution_contexts: Vec<ExecutionContext> = self.execution_contexts.values().cloned().collect();
+        execution_contexts.sort_by_key(|context| context.id);
+        SessionState {
+            target_id: self.target_id.clone(),
+            session_id: self.session_id.clone(),
+            target_type: self.target_type.clone(),
+            enabled_domains: self.enabled_domains.clone(),
+            main_frame: self.main_frame_id.as_ref().and_then(|id| self.frames.get(id)).cloned(),
+            frames,
+            execution_contexts,
+            detached: self.detached,
+        }
+    }
+
+    fn detached_error(&self) -> ProtocolError {
+        ProtocolError::TargetOrSessionNotFound(self.target_id.clone())
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &serde_json::Value) -> Result<T, ProtocolError> {
+    T::deserialize(value).map_err(|e| ProtocolError::EventParseError {
+        reason: e.to_string(),
+        event_fragment: value.to_string().chars().take(100).collect(),
+    })
+}
+
+impl Actor for SessionActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if self.detached {
+            return;
+        }
+        log::debug!("Session {} (target {}, {}) started", self.session_id, self.target_id, self.target_type);
+        self.sync(ctx);
+    }
+}
+
+impl Supervised for SessionActor {
+    fn restarting(&mut self, _ctx: &mut Context<Self>) {
+        log::warn!("Session {} (target {}) restarting, resynchronizing its state", self.session_id, self.target_id);
+        self.held_frame_events = None;
+        self.main_frame_id = None;
+        self.frames.clear();
+        self.execution_contexts.clear();
+    }
+}
+
+impl Handler<SessionCommand> for SessionActor {
+    type Result = ResponseActFuture<Self, Result<serde_json::Value, ProtocolError>>;
+
+    fn handle(&mut self, msg: SessionCommand, _ctx: &mut Context<Self>) -> Self::Result {
+        if self.detached {
+            return Box::pin(fut::ready(Err(self.detached_error())));
+        }
+        let request = self.execute_command(&msg.method, msg.params, msg.idempotent);
+        Box::pin(
+            async move {
+                request.await.map_err(|e| ProtocolError::Internal(format!("Command actor unavailable: {}", e)))?
+            }
+            .into_actor(self)
+            .map(move |result, act, _ctx| {
+                if result.is_ok() {
+                    if let Some(domain) = msg.method.strip_suffix(".enable") {
+                        act.enabled_domains.insert(domain.to_string());
+                    } else if let Some(domain) = msg.method.strip_suffix(".disable") {
+                        act.enabled_domains.remove(domain);
+                    }
+                }
+                result
+            }),
+        )
+    }
+}
+
+impl Handler<GetSessionState> for SessionActor {
+    type Result = MessageResult<GetSessionState>;
+
+    fn handle(&mut self, _msg: GetSessionState, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.state())
+    }
+}
+
+impl Handler<CloseTarget> for SessionActor {
+    type Result = ResponseFuture<Result<(), ProtocolError>>;
+
+    fn handle(&mut self, _msg: CloseTarget, _ctx: &mut Context<Self>) -> Self::Result {
+        if self.detached {
+            let error = self.detached_error();
+            return Box::pin(async move { Err(error) });
+        }
+        // A browser-level command: the target is addressed by id, not through its session
+        let request = self.commands.send(ExecuteCommand {
+            session_id: None,
+            method: "Target.closeTarget".to_string(),
+            params: serde_json::json!({ "targetId": self.target_id }),
+            idempotent: false,
+        });
+        Box::pin(async move {
+            request.await.map_err(|e| ProtocolError::Internal(format!("Command actor unavailable: {}", e)))??;
+            Ok(())
+        })
+    }
+}
+
+impl Handler<TargetDetached> for SessionActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: TargetDetached, _ctx: &mut Context<Self>) {
+        log::debug!("Session {} (target {}) detached", self.session_id, self.target_id);
+        // Keep running while handles remain, so they get a clear error instead of a closed mailbox
+        self.detached = true;
+        self.frames.clear();
+        self.main_frame_id = None;
+        self.execution_contexts.clear();
+    }
+}
+
+impl Handler<ProtocolEvent> for SessionActor {
+    type Result = ();
+
+    fn handle(&mut self, event: ProtocolEvent, ctx: &mut Context<Self>) {
+        if self.detached {
+            return;
+        }
+        if let Some(held) = &mut self.held_frame_events {
+            if event.method.starts_with("Page.") {
+                held.push(event);
+                return;
+            }
+        }
+        if let Err(e) = self.apply(&event) {
+            log::error!("Session {}: state out of sync after {}: {}", self.session_id, event.method, e);
+            ctx.stop(); // Restarted by the supervisor
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{tests::launch, GetPages, NewPage};
+    use mock_cdp::{MockCdpServer, Reply, SESSION_PREFIX};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn target_of(request: &mock_cdp::Request) -> String {
+        request.session_id.as_deref().unwrap_or_default().trim_start_matches(SESSION_PREFIX).to_string()
+    }
+
+    /// Answers `Page.getFrameTree` with a lone main frame named after the target.
+    fn serve_frame_trees(server: &MockCdpServer) {
+        server.on("Page.getFrameTree", |request| {
+            Reply::result(json!({ "frameTree": { "frame": { "id": target_of(request), "url": "about:blank" } } }))
+        });
+    }
+
+    async fn synced_state(session: &Addr<SessionActor>) -> SessionState {
+        for _ in 0..100 {
+            let state = session.send(GetSessionState).await.unwrap();
+            if state.main_frame.is_some() {
+                return state;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("frame tree never loaded");
+    }
+
+    #[actix::test]
+    async fn test_frame_events_racing_the_frame_tree_are_applied_on_top_of_it() {
+        let server = MockCdpServer::start().await.unwrap();
+        serve_frame_trees(&server);
+        // Reaches the session before the Page.getFrameTree response does
+        server.on("Page.enable", |request| {
+            Reply::result(json!({})).then_emit("Page.frameAttached", json!({ "frameId": "CHILD", "parentFrameId": target_of(request) }))
+        });
+        let (_supervisor, route) = launch(&server).await;
+        let target = route.browser.unwrap().send(NewPage { url: "about:blank".to_string() }).await.unwrap().unwrap();
+
+        let state = synced_state(&target.session).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let frames: Vec<_> = state.frames.iter().map(|frame| frame.id.as_str()).collect();
+        assert_eq!(frames, ["CHILD", target.target_id.as_str()]);
+        // No restart, so the tree was only loaded once
+        let loads = server.received().iter()
+            .filter(|r| r.method == "Page.getFrameTree" && r.session_id.as_deref() == Some(target.session_id.as_str()))
+            .count();
+        assert_eq!(loads, 1);
+    }
+
+    fn event(method: &str, params: serde_json::Value) -> ProtocolEvent {
+        ProtocolEvent { session_id: Some("SESSION-T".to_string()), method: method.to_string(), params }
+    }
+
+    fn frame_ids(state: &SessionState) -> Vec<&str> {
+        state.frames.iter().map(|frame| frame.id.as_str()).collect()
+    }
+
+    #[actix::test]
+    async fn test_apply_tracks_frames_and_execution_contexts() {
+        let commands = Context::<CommandActor>::new().address();
+        let mut session = SessionActor::new("T".to_string(), "SESSION-T".to_string(), "page".to_string(), commands);
+
+        session.apply(&event("Page.frameNavigated", json!({ "frame": { "id": "MAIN", "url": "https://a.test/" } }))).unwrap();
+        session.apply(&event("Page.frameAttached", json!({ "frameId": "CHILD", "parentFrameId": "MAIN" }))).unwrap();
+        session.apply(&event("Page.frameAttached", json!({ "frameId": "GRANDCHILD", "parentFrameId": "CHILD" }))).unwrap();
+        let unknown_parent = session.apply(&event("Page.frameAttached", json!({ "frameId": "X", "parentFrameId": "NOPE" })));
+        assert!(matches!(unknown_parent, Err(ProtocolError::Internal(_))), "{:?}", unknown_parent);
+        let malformed = session.apply(&event("Page.frameNavigated", json!({})));
+        assert!(matches!(malformed, Err(ProtocolError::EventParseError { .. })), "{:?}", malformed);
+        let state = session.state();
+        assert_eq!(state.main_frame.as_ref().unwrap().url, "https://a.test/");
+        assert_eq!(frame_ids(&state), ["CHILD", "GRANDCHILD", "MAIN"]);
+
+        let context = |id: i64, frame_id: &str, is_default: bool| {
+            json!({ "context": { "id": id, "origin": "https://a.test", "auxData": { "frameId": frame_id, "isDefault": is_default } } })
+        };
+        session.apply(&event("Runtime.executionContextCreated", context(1, "MAIN", false))).unwrap();
+        session.apply(&event("Runtime.executionContextCreated", context(2, "MAIN", true))).unwrap();
+        session.apply(&event("Runtime.executionContextCreated", context(3, "CHILD", true))).unwrap();
+        assert_eq!(session.state().main_context().map(|context| context.id), Some(2));
+
+        // Children are detached with their parent
+        session.apply(&event("Page.frameDetached", json!({ "frameId": "CHILD" }))).unwrap();
+        session.apply(&event("Runtime.executionContextDestroyed", json!({ "executionContextId": 2 }))).unwrap();
+        let state = session.state();
+        assert_eq!(frame_ids(&state), ["MAIN"]);
+        assert_eq!(state.execution_contexts.iter().map(|context| context.id).collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(state.main_context(), None);
+
+        session.apply(&event("Runtime.executionContextsCleared", json!({}))).unwrap();
+        // A new main document replaces the whole tree
+        session.apply(&event("Page.frameNavigated", json!({ "frame": { "id": "NEXT", "url": "https://b.test/" } }))).unwrap();
+        let state = session.state();
+        assert_eq!(frame_ids(&state), ["NEXT"]);
+        assert_eq!(state.main_frame.unwrap().id, "NEXT");
+        assert!(state.execution_contexts.is_empty());
+    }
+
+    #[actix::test]
+    async fn test_sessions_attach_to_pages_and_detach_when_they_go_away() {
+        let server = MockCdpServer::start().await.unwrap();
+        serve_frame_trees(&server);
+        let (_supervisor, route) = launch(&server).await;
+        let browser = route.browser.unwrap();
+        let target = browser.send(NewPage { url: "about:blank".to_string() }).await.unwrap().unwrap();
+        assert_eq!(target.session_id, format!("{}{}", SESSION_PREFIX, target.target_id));
+        let state = synced_state(&target.session).await;
+        assert_eq!(state.enabled_domains.iter().map(String::as_str).collect::<Vec<_>>(), ["Page", "Runtime"]);
+        let other = browser.send(NewPage { url: "about:blank".to_string() }).await.unwrap().unwrap();
+        let pages = || async {
+            let mut pages: Vec<_> = browser.send(GetPages).await.unwrap().into_iter().map(|page| page.target_id).collect();
+            pages.sort();
+            pages
+        };
+        assert_eq!(pages().await, [target.target_id.as_str(), other.target_id.as_str()]);
+
+        target.session.send(CloseTarget).await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pages().await, [other.target_id.as_str()]);
+        let state = target.session.send(GetSessionState).await.unwrap();
+        assert!(state.detached && state.frames.is_empty());
+        let closed = target.session.send(SessionCommand::new("Runtime.evaluate", json!({ "expression": "1" }))).await.unwrap();
+        assert!(matches!(closed, Err(ProtocolError::TargetOrSessionNotFound(ref id)) if *id == target.target_id), "{:?}", closed);
+        assert!(matches!(target.session.send(CloseTarget).await.unwrap(), Err(ProtocolError::TargetOrSessionNotFound(_))));
+
+        // Older browsers only name the session
+        server.emit("Target.detachedFromTarget", json!({ "sessionId": other.session_id }), None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(pages().await.is_empty());
+        assert!(other.session.send(GetSessionState).await.unwrap().detached);
+    }
+
+    #[actix::test]
+    async fn test_inconsistent_events_restart_the_session_and_resync_it() {
+        let server = MockCdpServer::start().await.unwrap();
+        serve_frame_trees(&server);
+        let (_supervisor, route) = launch(&server).await;
+        let target = route.browser.unwrap().send(NewPage { url: "about:blank".to_string() }).await.unwrap().unwrap();
+        synced_state(&target.session).await;
+        target.session.send(SessionCommand::new("Network.enable", json!({}))).await.unwrap().unwrap();
+        let count = |method: &str| {
+            server.received().iter().filter(|r| r.method == method && r.session_id.as_deref() == Some(target.session_id.as_str())).count()
+        };
+
+        server.emit("Page.frameAttached", json!({ "frameId": "X", "parentFrameId": "NOPE" }), Some(&target.session_id));
+        for _ in 0..100 {
+            if count("Page.getFrameTree") == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Restarted in place: the same address, resynced from the browser
+        let state = synced_state(&target.session).await;
+        assert_eq!(frame_ids(&state), [target.target_id.as_str()]);
+        assert!(state.enabled_domains.contains("Network"));
+        assert_eq!(count("Page.getFrameTree"), 2);
+        assert_eq!(count("Network.enable"), 2);
+        assert!(!state.detached);
+    }
+}
@@ -1,10 +1,12 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use crate::error::{CoreError, ConfigError}; // Use CoreError for config loading result
+use crate::error::CoreError; // Use CoreError for config loading result
 
 // Re-export Config for easier access
 pub use config::ConfigError;
+// The transport sections live with the transport's other shared types
+pub use janus_common::config::{RecordingConfig, TlsConfig, WebSocketConfig};
 
 // --- Struct Definitions (copied from design) ---
 
@@ -20,6 +22,9 @@ pub struct Config {
     pub transport: TransportConfig,
     #[serde(default)]
     pub actor_system: ActorSystemConfig,
+    /// `[plugins.<name>]` sections, keyed by plugin name.
+    #[serde(default)]
+    pub plugins: HashMap<String, PluginConfig>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -27,6 +32,7 @@ pub struct Config {
 pub struct GlobalConfig {
     pub log_level: String,
     pub default_command_timeout_ms: u64,
+    pub command_retry: RetryConfig,
 }
 
 impl Default for GlobalConfig {
@@ -34,6 +40,40 @@ impl Default for GlobalConfig {
         Self {
             log_level: "info".to_string(),
             default_command_timeout_ms: 30_000, // 30 seconds
+            command_retry: RetryConfig::default(),
+        }
+    }
+}
+
+/// Retries of commands lost to transient failures. Only idempotent commands are retried:
+/// the methods in `idempotent_methods`, and commands their caller flags as idempotent
+/// (`Runtime.evaluate` only with `returnByValue`).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Attempts per command, including the first. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, multiplied by `backoff_multiplier` for each further one.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub backoff_multiplier: f64,
+    pub idempotent_methods: Vec<String>,
+    /// Browser error codes that are retried. Send failures and reconnects always are.
+    pub retry_on_error_codes: Vec<i64>,
+    /// Also retry commands whose response timed out, e.g. because a frame was dropped.
+    pub retry_on_timeout: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2_000, // 2 seconds
+            backoff_multiplier: 2.0,
+            idempotent_methods: vec!["DOM.describeNode".to_string(), "Target.getTargets".to_string()],
+            retry_on_error_codes: Vec::new(),
+            retry_on_timeout: false,
         }
     }
 }
@@ -63,6 +103,45 @@ pub struct BrowserSpecificConfig {
     pub user_data_dir: Option<String>,
     pub args: Option<Vec<String>>,
     pub protocol_port: Option<u16>,
+    /// Extra headers for the WebSocket upgrade request (e.g. `Authorization`).
+    pub headers: HashMap<String, String>,
+    /// Query parameters appended to the connection URL (e.g. hosted-service tokens).
+    pub query_params: HashMap<String, String>,
+    pub rate_limit: RateLimitConfig,
+}
+
+/// Token-bucket limits on the commands sent to one browser. Commands over budget are
+/// queued, in order, until tokens are available again.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Budget shared by all commands, heavy ones included. `None` leaves commands unlimited.
+    pub commands: Option<TokenBucketConfig>,
+    /// Extra budget that `heavy_methods` need on top of `commands`.
+    pub heavy: Option<TokenBucketConfig>,
+    pub heavy_methods: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            commands: None,
+            heavy: None,
+            heavy_methods: vec![
+                "Page.captureScreenshot".to_string(),
+                "Page.printToPDF".to_string(),
+                "DOMSnapshot.captureSnapshot".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenBucketConfig {
+    /// Sustained rate, in commands per second.
+    pub per_second: f64,
+    /// Commands that may go out at once after a quiet period.
+    pub burst: u32,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -70,6 +149,8 @@ pub struct BrowserSpecificConfig {
 pub struct TransportConfig {
     pub connect_timeout_ms: u64,
     pub websocket: WebSocketConfig,
+    pub tls: TlsConfig,
+    pub recording: RecordingConfig,
 }
 
  impl Default for TransportConfig {
@@ -77,36 +158,71 @@ pub struct TransportConfig {
         Self {
             connect_timeout_ms: 10_000, // 10 seconds
             websocket: WebSocketConfig::default(),
+            tls: TlsConfig::default(),
+            recording: RecordingConfig::default(),
         }
     }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
-pub struct WebSocketConfig {
-    pub max_message_size: Option<usize>,
-    pub accept_unmasked_frames: bool,
+pub struct ActorSystemConfig {
+    pub default_mailbox_capacity: usize,
+    pub shutdown: ShutdownConfig,
 }
 
-impl Default for WebSocketConfig {
+impl Default for ActorSystemConfig {
     fn default() -> Self {
         Self {
-            max_message_size: Some(64 * 1024 * 1024), // 64 MiB
-            accept_unmasked_frames: false,
+            default_mailbox_capacity: 100,
+            shutdown: ShutdownConfig::default(),
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// How the supervisor winds down the actor tree.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
-pub struct ActorSystemConfig {
-    pub default_mailbox_capacity: usize,
+pub struct ShutdownConfig {
+    /// How long in-flight commands may take to finish before they are failed.
+    pub drain_timeout_ms: u64,
+    /// Send `Browser.close` to every connected browser before closing its connection.
+    /// Pipe-mode browsers are launched by the connection and always exit with it.
+    pub close_browsers: bool,
+    /// Shut down on SIGINT/SIGTERM (Ctrl-C elsewhere) and then stop the actix system.
+    /// Off by default, so the host application keeps its own signal handling.
+    pub handle_signals: bool,
 }
 
-impl Default for ActorSystemConfig {
+impl Default for ShutdownConfig {
     fn default() -> Self {
         Self {
-            default_mailbox_capacity: 100,
+            drain_timeout_ms: 10_000, // 10 seconds
+            close_browsers: false,
+            handle_signals: false,
+        }
+    }
+}
+
+
+/// A plugin's config section. Keys other than `enabled` and `order` are passed to the
+/// plugin's `configure`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PluginConfig {
+    pub enabled: bool,
+    /// Plugins run in ascending order; equal orders keep registration order.
+    pub order: i32,
+    #[serde(flatten)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            order: 0,
+            settings: serde_json::Map::new(),
         }
     }
 }
@@ -124,7 +240,7 @@ pub fn load_config(source_path: Option<PathBuf>) -> Result<Config, CoreError> {
         // However, keeping them can make defaults more explicit if desired.
         .set_default("global.log_level", GlobalConfig::default().log_level).map_err(CoreError::Config)?
         .set_default("transport.connect_timeout_ms", TransportConfig::default().connect_timeout_ms).map_err(CoreError::Config)?
-        .set_default("actor_system.default_mailbox_capacity", ActorSystemConfig::default().default_mailbox_capacity).map_err(CoreError::Config)?;
+        .set_default("actor_system.default_mailbox_capacity", ActorSystemConfig::default().default_mailbox_capacity as u64).map_err(CoreError::Config)?;
 
 
     // Load from specified file path if provided
@@ -1,53 +1,10 @@
 // JanusClient/janus-client/crates/janus-core/src/error/mod.rs
 use thiserror::Error;
-use std::time::Duration; // Needed if timeout details are included
 
 // Re-export for convenience elsewhere
 pub use config::ConfigError;
 pub use actix::MailboxError;
-
-// --- Transport Error (L3) ---
-#[derive(Error, Debug, Clone)] // Clone might be useful for some scenarios, e.g., state reporting
-pub enum TransportError {
-    #[error("Invalid URL format: {0}")]
-    InvalidUrl(String),
-
-    #[error("Connection failed: {0}")]
-    ConnectionFailed(String),
-
-    #[error("Connection closed: {reason:?}")]
-    ConnectionClosed { reason: Option<String> },
-
-    #[error("Not connected")]
-    NotConnected,
-
-    #[error("I/O error: {0}")]
-    Io(String), // Wrap std/tokio IO error strings
-
-    #[error("TLS error: {0}")]
-    TlsError(String),
-
-    #[error("WebSocket protocol error: {0}")]
-    WebSocket(String), // Wrap tungstenite::Error strings (protocol related)
-
-    #[error("Failed to send message: {0}")]
-    SendFailed(String),
-
-    #[error("Failed to receive message: {0}")]
-    ReceiveFailed(String),
-
-    #[error("Operation timed out: {0}")]
-    Timeout(String), // Specific timeout details
-
-    #[error("Serialization/Deserialization error: {0}")]
-    Serde(String), // e.g., invalid UTF8, framing issues
-
-    #[error("Unsupported URL scheme: {0}")]
-    UnsupportedScheme(String),
-
-    #[error("Internal transport error: {0}")]
-    Internal(String),
-}
+pub use janus_common::error::TransportError;
 
 // --- Protocol Error (L2/Core Interaction) ---
 #[derive(Error, Debug, Clone)] // Clone might be useful
@@ -77,6 +34,18 @@ pub enum ProtocolError {
     #[error("Waiting for command response timed out")]
     Timeout, // Specific to waiting for a protocol response
 
+    /// The command could not be written to the connection.
+    #[error("Failed to send command: {0}")]
+    SendFailed(TransportError),
+
+    /// The connection dropped before the response arrived.
+    #[error("Connection lost while awaiting response: {0}")]
+    ConnectionLost(String),
+
+    /// The client is shutting down and no longer accepts commands.
+    #[error("Client is shutting down")]
+    ShuttingDown,
+
     #[error("Target or session not found: {0}")]
     TargetOrSessionNotFound(String),
 
@@ -0,0 +1,269 @@
+// JanusClient/janus-client/crates/janus-core/src/plugin/mod.rs
+//! Plugins: in-process extensions that see every command and event of every connection.
+//!
+//! Plugins are registered in a `PluginRegistry` before the `SupervisorActor` starts. At
+//! startup the registry is resolved against the `[plugins.<name>]` sections of the
+//! config: disabled plugins are dropped, the rest are configured with their section and
+//! ordered by `order` (ties keep registration order). Every `CommandActor` and `EventActor`
+//! then runs the resulting chain.
+//!
+//! Hooks run synchronously inside the actors, so they should be quick.
+
+use crate::actor::{ExecuteCommand, ProtocolEvent};
+use crate::config::PluginConfig;
+use crate::error::{CoreError, ProtocolError};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub trait Plugin: Send + Sync + 'static {
+    /// Unique name; also the key of the plugin's config section.
+    fn name(&self) -> &str;
+
+    /// Called once at startup with the plugin's config section, minus `enabled` and `order`
+    /// (an empty object if there is none). An error leaves the plugin out.
+    fn configure(&mut self, _settings: &serde_json::Value) -> Result<(), CoreError> {
+        Ok(())
+    }
+
+    /// Custom domains answered by `handle_command` instead of the browser, e.g. `"Janus"`
+    /// for `Janus.*` commands.
+    fn domains(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Answers a command of one of `domains()`.
+    fn handle_command(&self, command: &ExecuteCommand) -> Result<serde_json::Value, ProtocolError> {
+        Err(ProtocolError::InvalidRequest(format!("Plugin '{}' does not handle {}", self.name(), command.method)))
+    }
+
+    /// Sees every outgoing command and may modify it. An error rejects the command.
+    fn on_command(&self, _command: &mut ExecuteCommand) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    /// Sees every incoming event and may modify it. Returning `false` drops the event;
+    /// later plugins and subscribers never see it.
+    fn on_event(&self, _event: &mut ProtocolEvent) -> bool {
+        true
+    }
+}
+
+/// Collects plugins before startup.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl std::fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.plugins.iter().map(|plugin| plugin.name())).finish()
+    }
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(mut self, plugin: impl Plugin) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Applies the config: drops disabled plugins, configures and orders the rest.
+    pub fn load(self, config: &HashMap<String, PluginConfig>) -> Plugins {
+        let mut loaded: Vec<(i32, Arc<dyn Plugin>)> = Vec::new();
+        for mut plugin in self.plugins {
+            let name = plugin.name().to_string();
+            let plugin_config = config.get(&name).cloned().unwrap_or_default();
+            if !plugin_config.enabled {
+                log::info!("Plugin '{}' is disabled", name);
+                continue;
+            }
+            if loaded.iter().any(|(_, other)| other.name() == name) {
+                log::error!("Plugin '{}' registered twice, ignoring the second one", name);
+                continue;
+            }
+            if let Err(e) = plugin.configure(&serde_json::Value::Object(plugin_config.settings)) {
+                log::error!("Plugin '{}' failed to configure and is not loaded: {}", name, e);
+                continue;
+            }
+            loaded.push((plugin_config.order, Arc::from(plugin)));
+        }
+        for name in config.keys() {
+            if !loaded.iter().any(|(_, plugin)| plugin.name() == name) && config[name].enabled {
+                log::warn!("Config section for plugin '{}', which is not registered", name);
+            }
+        }
+        // Stable, so equal `order`s keep registration order
+        loaded.sort_by_key(|(order, _)| *order);
+
+        let chain: Vec<Arc<dyn Plugin>> = loaded.into_iter().map(|(_, plugin)| plugin).collect();
+        let mut domains = HashMap::new();
+        for (index, plugin) in chain.iter().enumerate() {
+            for domain in plugin.domains() {
+                if let Some(&owner) = domains.get(&domain) {
+                    let owner: &Arc<dyn Plugin> = &chain[owner];
+                    log::warn!("Domain '{}' is handled by plugin '{}', ignoring plugin '{}'", domain, owner.name(), plugin.name());
+                    continue;
+                }
+                domains.insert(domain, index);
+            }
+        }
+        let plugins = Plugins { inner: Arc::new(PluginChain { chain, domains }) };
+        log::info!("Loaded plugins: {:?}", plugins.names());
+        plugins
+    }
+}
+
+/// The loaded plugins, in order. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    inner: Arc<PluginChain>,
+}
+
+#[derive(Default)]
+struct PluginChain {
+    chain: Vec<Arc<dyn Plugin>>,
+    /// Custom domain -> index into `chain` of the plugin answering it.
+    domains: HashMap<String, usize>,
+}
+
+impl std::fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Plugins").field(&self.names()).finish()
+    }
+}
+
+impl Plugins {
+    pub fn names(&self) -> Vec<&str> {
+        self.inner.chain.iter().map(|plugin| plugin.name()).collect()
+    }
+
+    /// Runs every `on_command` hook in order, stopping at the first rejection.
+    pub(crate) fn on_command(&self, command: &mut ExecuteCommand) -> Result<(), ProtocolError> {
+        self.inner.chain.iter().try_for_each(|plugin| plugin.on_command(command))
+    }
+
+    /// The plugin answering `method`'s domain, if any.
+    pub(crate) fn command_handler(&self, method: &str) -> Option<&Arc<dyn Plugin>> {
+        let domain = method.split('.').next()?;
+        self.inner.domains.get(domain).map(|&index| &self.inner.chain[index])
+    }
+
+    /// Runs every `on_event` hook in order; `false` if one of them dropped the event.
+    pub(crate) fn on_event(&self, event: &mut ProtocolEvent) -> bool {
+        self.inner.chain.iter().all(|plugin| plugin.on_event(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Appends its tag to every command's `tags` param, rejecting `Forbidden.*` commands.
+    struct Tagger {
+        name: &'static str,
+        tag: String,
+    }
+
+    impl Tagger {
+        fn new(name: &'static str) -> Self {
+            Self { name, tag: name.to_string() }
+        }
+    }
+
+    impl Plugin for Tagger {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn configure(&mut self, settings: &serde_json::Value) -> Result<(), CoreError> {
+            match &settings["tag"] {
+                serde_json::Value::Null => Ok(()),
+                serde_json::Value::String(tag) => {
+                    self.tag = tag.clone();
+                    Ok(())
+                }
+                other => Err(CoreError::Config(::config::ConfigError::Message(format!("tag must be a string, got {}", other)))),
+            }
+        }
+
+        fn on_command(&self, command: &mut ExecuteCommand) -> Result<(), ProtocolError> {
+            if command.method.starts_with("Forbidden.") {
+                return Err(ProtocolError::InvalidRequest(format!("{} rejected by {}", command.method, self.name)));
+            }
+            command.params["tags"].as_array_mut().unwrap().push(json!(self.tag));
+            Ok(())
+        }
+
+        fn on_event(&self, event: &mut ProtocolEvent) -> bool {
+            event.params["seenBy"] = json!(self.name);
+            !event.method.starts_with("Noise.")
+        }
+    }
+
+    fn plugin_config(toml: &str) -> HashMap<String, PluginConfig> {
+        #[derive(serde::Deserialize)]
+        struct Sections {
+            plugins: HashMap<String, PluginConfig>,
+        }
+        ::config::Config::builder()
+            .add_source(::config::File::from_str(toml, ::config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Sections>()
+            .unwrap()
+            .plugins
+    }
+
+    fn command(method: &str) -> ExecuteCommand {
+        ExecuteCommand { session_id: None, method: method.to_string(), params: json!({ "tags": [] }), idempotent: false }
+    }
+
+    #[test]
+    fn test_load_orders_configures_and_drops_disabled_plugins() {
+        let config = plugin_config(
+            r#"
+            [plugins.first]
+            order = 5
+            tag = "configured"
+
+            [plugins.disabled]
+            enabled = false
+
+            [plugins.misconfigured]
+            tag = 1
+            "#,
+        );
+        let plugins = PluginRegistry::new()
+            .register(Tagger::new("first"))
+            .register(Tagger::new("second"))
+            .register(Tagger::new("disabled"))
+            .register(Tagger::new("misconfigured"))
+            .register(Tagger::new("third"))
+            .register(Tagger::new("second"))
+            .load(&config);
+        // Ascending order, ties in registration order
+        assert_eq!(plugins.names(), ["second", "third", "first"]);
+
+        let mut allowed = command("Page.navigate");
+        plugins.on_command(&mut allowed).unwrap();
+        assert_eq!(allowed.params["tags"], json!(["second", "third", "configured"]));
+        let mut rejected = command("Forbidden.method");
+        assert!(matches!(plugins.on_command(&mut rejected), Err(ProtocolError::InvalidRequest(_))));
+        assert_eq!(rejected.params["tags"], json!([]));
+    }
+
+    #[test]
+    fn test_events_stop_at_the_plugin_dropping_them() {
+        let plugins = PluginRegistry::new().register(Tagger::new("a")).register(Tagger::new("b")).load(&HashMap::new());
+        let mut event = ProtocolEvent { session_id: None, method: "Page.loadEventFired".to_string(), params: json!({}) };
+        assert!(plugins.on_event(&mut event));
+        assert_eq!(event.params["seenBy"], "b");
+        let mut noise = ProtocolEvent { session_id: None, method: "Noise.tick".to_string(), params: json!({}) };
+        assert!(!plugins.on_event(&mut noise));
+        assert_eq!(noise.params["seenBy"], "a");
+    }
+}
@@ -3,6 +3,9 @@ use serde_json::Value;
 use thiserror::Error;
 use janus_core::error::{CoreError, ProtocolError, TransportError}; // Import internal errors
 
+mod session_page;
+pub use session_page::SessionPage;
+
 // --- Placeholder Types (Define properly or remove if not needed yet) ---
 #[derive(Debug, Clone)]
 pub struct ElementHandle { /* Opaque handle representation */ pub internal_id: String }
@@ -12,8 +15,8 @@ pub struct ConsoleMessage { /* Details of console message */ pub text: String }
 pub enum ScreenshotFormat { Jpeg, Png, Webp }
 #[derive(Debug, Clone, Default)]
 pub struct ScreenshotOptions { /* Quality, clip rect etc. */ pub quality: Option<u8> }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct SubscriptionId(pub u64); // Example simple subscription ID
+// Issued by the core EventActor, so the same type is used on both layers
+pub use janus_core::actor::SubscriptionId;
 
 // --- L1 API Error Type ---
 #[derive(Error, Debug)]
@@ -61,6 +64,7 @@ impl From<CoreError> for ApiError {
                 TransportError::SendFailed(reason) |
                 TransportError::ReceiveFailed(reason) => ApiError::ProtocolError(format!("Message transport failed: {}", reason)), // Map to ProtocolError as it affects commands/events
                 TransportError::Io(reason) => ApiError::ConnectionFailed(format!("Network I/O error: {}", reason)),
+                TransportError::AuthenticationFailed { status, detail } => ApiError::ConnectionFailed(format!("Authentication failed (HTTP {}): {}", status, detail)),
                 TransportError::Internal(reason) => ApiError::InternalError(format!("Transport layer internal error: {}", reason)),
             },
 
@@ -72,7 +76,10 @@ impl From<CoreError> for ApiError {
                 ProtocolError::ResponseParseError { reason, .. } |
                 ProtocolError::EventParseError { reason, .. } |
                 ProtocolError::SerializationError(reason) => ApiError::ProtocolError(format!("Protocol serialization/parsing error: {}", reason)),
-                ProtocolError::TargetOrSessionNotFound(id) => ApiError::TargetNotFound, // Specific target not found error
+                ProtocolError::SendFailed(e) => ApiError::ConnectionFailed(format!("Failed to send command: {}", e)),
+                ProtocolError::ConnectionLost(reason) => ApiError::ConnectionFailed(format!("Connection lost: {}", reason)),
+                ProtocolError::ShuttingDown => ApiError::ConnectionFailed("Client is shutting down".to_string()),
+                ProtocolError::TargetOrSessionNotFound(_) => ApiError::TargetNotFound, // Specific target not found error
                 ProtocolError::Internal(reason) => ApiError::InternalError(format!("Protocol layer internal error: {}", reason)),
             },
 
@@ -0,0 +1,235 @@
+//! `Page` backed by the target's `SessionActor`.
+
+use crate::{ApiError, ElementHandle, Page, ScreenshotFormat, ScreenshotOptions};
+use actix::Addr;
+use async_trait::async_trait;
+use base64::Engine;
+use janus_core::actor::{CloseTarget, GetSessionState, SessionActor, SessionCommand, SessionState, TargetSession};
+use janus_core::CoreError;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How long `wait_for_selector` waits when no timeout is given.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+const SELECTOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A page, talking to the browser through its `SessionActor`.
+#[derive(Debug, Clone)]
+pub struct SessionPage {
+    target_id: String,
+    session: Addr<SessionActor>,
+}
+
+impl SessionPage {
+    pub fn new(target: TargetSession) -> Self {
+        Self { target_id: target.target_id, session: target.session }
+    }
+
+    async fn send(&self, command: SessionCommand) -> Result<Value, ApiError> {
+        let result = self.session.send(command).await.map_err(CoreError::from)?;
+        Ok(result.map_err(CoreError::from)?)
+    }
+
+    async fn command(&self, method: &str, params: Value) -> Result<Value, ApiError> {
+        self.send(SessionCommand::new(method, params)).await
+    }
+
+    /// A command without side effects, retried on transient failures.
+    async fn query(&self, method: &str, params: Value) -> Result<Value, ApiError> {
+        self.send(SessionCommand::new(method, params).idempotent()).await
+    }
+
+    async fn state(&self) -> Result<SessionState, ApiError> {
+        Ok(self.session.send(GetSessionState).await.map_err(CoreError::from)?)
+    }
+
+    /// Evaluates `expression` in the page; `by_value` returns the JSON value instead of a remote object.
+    async fn evaluate(&self, expression: &str, by_value: bool) -> Result<Value, ApiError> {
+        let result = self.command("Runtime.evaluate", json!({
+            "expression": expression,
+            "returnByValue": by_value,
+            "awaitPromise": true,
+        })).await?;
+        script_result(result)
+    }
+
+    /// Evaluates a side-effect free `expression` and returns its JSON value.
+    async fn read(&self, expression: &str) -> Result<Value, ApiError> {
+        let result = self.query("Runtime.evaluate", json!({
+            "expression": expression,
+            "returnByValue": true,
+            "awaitPromise": true,
+        })).await?;
+        Ok(script_result(result)?["value"].take())
+    }
+
+    async fn navigate_history(&self, offset: i64) -> Result<(), ApiError> {
+        let history = self.query("Page.getNavigationHistory", json!({})).await?;
+        let index = history["currentIndex"].as_i64().unwrap_or(0) + offset;
+        let entry = usize::try_from(index).ok().and_then(|index| history["entries"].get(index));
+        match entry.and_then(|entry| entry["id"].as_i64()) {
+            Some(entry_id) => self.command("Page.navigateToHistoryEntry", json!({ "entryId": entry_id })).await.map(|_| ()),
+            None => Err(ApiError::NavigationError(format!("No history entry at offset {}", offset))),
+        }
+    }
+}
+
+/// Unwraps a `Runtime.evaluate`/`Runtime.callFunctionOn` result, surfacing thrown exceptions.
+fn script_result(mut result: Value) -> Result<Value, ApiError> {
+    if let Some(details) = result.get("exceptionDetails") {
+        let message = details["exception"]["description"].as_str()
+            .or_else(|| details["text"].as_str())
+            .unwrap_or("Uncaught exception");
+        return Err(ApiError::ScriptError(message.to_string()));
+    }
+    Ok(result["result"].take())
+}
+
+#[async_trait]
+impl Page for SessionPage {
+    async fn navigate(&self, url: &str) -> Result<(), ApiError> {
+        let result = self.command("Page.navigate", json!({ "url": url })).await?;
+        match result["errorText"].as_str() {
+            Some(error) => Err(ApiError::NavigationError(error.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    async fn reload(&self) -> Result<(), ApiError> {
+        self.command("Page.reload", json!({})).await.map(|_| ())
+    }
+
+    async fn go_back(&self) -> Result<(), ApiError> {
+        self.navigate_history(-1).await
+    }
+
+    async fn go_forward(&self) -> Result<(), ApiError> {
+        self.navigate_history(1).await
+    }
+
+    async fn close(&self) -> Result<(), ApiError> {
+        let result = self.session.send(CloseTarget).await.map_err(CoreError::from)?;
+        Ok(result.map_err(CoreError::from)?)
+    }
+
+    fn id(&self) -> String {
+        self.target_id.clone()
+    }
+
+    async fn content(&self) -> Result<String, ApiError> {
+        let html = self.read("document.documentElement ? document.documentElement.outerHTML : ''").await?;
+        Ok(html.as_str().unwrap_or_default().to_string())
+    }
+
+    async fn evaluate_script(&self, script: &str) -> Result<Value, ApiError> {
+        let mut result = self.evaluate(script, true).await?;
+        Ok(result["value"].take())
+    }
+
+    async fn call_function(&self, function_declaration: &str, args: Vec<Value>) -> Result<Value, ApiError> {
+        // Run in the page's own context of the main frame, not an isolated world
+        let state = self.state().await?;
+        let context = state.main_context()
+            .ok_or_else(|| ApiError::ScriptError("The page has no execution context yet".to_string()))?;
+        let arguments: Vec<Value> = args.into_iter().map(|value| json!({ "value": value })).collect();
+        let result = self.command("Runtime.callFunctionOn", json!({
+            "functionDeclaration": function_declaration,
+            "arguments": arguments,
+            "executionContextId": context.id,
+            "returnByValue": true,
+            "awaitPromise": true,
+        })).await?;
+        let mut result = script_result(result)?;
+        Ok(result["value"].take())
+    }
+
+    async fn query_selector(&self, selector: &str) -> Result<Option<ElementHandle>, ApiError> {
+        let selector = serde_json::to_string(selector).map_err(|e| ApiError::InvalidParameters(e.to_string()))?;
+        let element = self.evaluate(&format!("document.querySelector({})", selector), false).await?;
+        Ok(element["objectId"].as_str().map(|id| ElementHandle { internal_id: id.to_string() }))
+    }
+
+    async fn wait_for_selector(&self, selector: &str, timeout_ms: Option<u64>) -> Result<ElementHandle, ApiError> {
+        let timeout = timeout_ms.map_or(DEFAULT_WAIT_TIMEOUT, Duration::from_millis);
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(element) = self.query_selector(selector).await? {
+                return Ok(element);
+            }
+            if tokio::time::Instant::now() + SELECTOR_POLL_INTERVAL > deadline {
+                return Err(ApiError::Timeout);
+            }
+            tokio::time::sleep(SELECTOR_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn url(&self) -> Result<String, ApiError> {
+        // Tracked from frame navigation events, no round trip to the browser
+        let state = self.state().await?;
+        Ok(state.main_frame.map(|frame| frame.url).unwrap_or_default())
+    }
+
+    async fn title(&self) -> Result<String, ApiError> {
+        let title = self.read("document.title").await?;
+        Ok(title.as_str().unwrap_or_default().to_string())
+    }
+
+    async fn take_screenshot(&self, format: ScreenshotFormat, options: Option<ScreenshotOptions>) -> Result<Vec<u8>, ApiError> {
+        let format = match format {
+            ScreenshotFormat::Jpeg => "jpeg",
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Webp => "webp",
+        };
+        let mut params = json!({ "format": format });
+        if let Some(quality) = options.and_then(|options| options.quality) {
+            params["quality"] = json!(quality);
+        }
+        let result = self.command("Page.captureScreenshot", params).await?;
+        let data = result["data"].as_str()
+            .ok_or_else(|| ApiError::ProtocolError("Page.captureScreenshot returned no data".to_string()))?;
+        base64::engine::general_purpose::STANDARD.decode(data)
+            .map_err(|e| ApiError::ProtocolError(format!("Invalid screenshot data: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::Actor;
+    use janus_core::actor::{GetConnectionRoute, LaunchConnection, NewPage, SupervisorActor};
+    use janus_transport::ConnectParams;
+    use mock_cdp::MockCdpServer;
+
+    /// Connects to `server` and opens a page on it.
+    async fn open_page(server: &MockCdpServer) -> (Addr<SupervisorActor>, TargetSession) {
+        let supervisor = SupervisorActor::new(None).start();
+        let params = ConnectParams::new(server.ws_url());
+        let id = supervisor.send(LaunchConnection { params, browser: None, owner: None }).await.unwrap().unwrap();
+        for _ in 0..100 {
+            if let Some(browser) = supervisor.send(GetConnectionRoute(id)).await.unwrap().and_then(|route| route.browser) {
+                let target = browser.send(NewPage { url: "about:blank".to_string() }).await.unwrap().unwrap();
+                return (supervisor, target);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("browser actor never started");
+    }
+
+    #[actix::test]
+    async fn test_page_commands_carry_the_session_and_fail_after_detach() {
+        let server = MockCdpServer::start().await.unwrap();
+        let (_supervisor, target) = open_page(&server).await;
+        let session_id = target.session_id.clone();
+        let page = SessionPage::new(target);
+
+        page.navigate("https://example.com/").await.unwrap();
+        let navigate = server.received().into_iter().find(|r| r.method == "Page.navigate").unwrap();
+        assert_eq!(navigate.session_id.as_deref(), Some(session_id.as_str()));
+
+        server.emit("Target.detachedFromTarget", json!({ "sessionId": session_id }), None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let detached = page.navigate("https://example.com/").await;
+        assert!(matches!(detached, Err(ApiError::TargetNotFound)), "{:?}", detached);
+        assert_eq!(server.received().iter().filter(|r| r.method == "Page.navigate").count(), 1);
+    }
+}
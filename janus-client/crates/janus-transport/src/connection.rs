@@ -1,17 +1,24 @@
-use actix::io::{ FramedWrite, WriteHandler }; // Add FramedWrite, WriteHandler
 use actix::prelude::*;
 use async_trait::async_trait;
+use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt; // Add StreamExt for stream handling
-use std::time::Duration;
-use janus_core::error::{TransportError, CoreError};
-use janus_core::actor::{SendRawMessage, IncomingRawMessage};
-use janus_core::error::ProtocolError; // Import ProtocolError if needed for SendRawMessage error mapping
-use tokio::time::timeout;
-use tokio::io::split; // For splitting the stream
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+use janus_common::error::TransportError;
+use janus_common::message::{SendRawMessage, IncomingRawMessage};
+use janus_common::config::TlsConfig;
+use crate::proxy::ProxyConfig;
+use crate::recorder::{Direction, TrafficRecorder};
+use crate::metrics::ConnectionMetrics;
+use tokio::sync::oneshot;
 
 // Use a specific ConnectionId type alias from janus-core or define locally
 pub type ConnectionId = u64;
 
+/// Connect timeout of `ConnectParams::new`.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 pub struct ConnectParams {
     pub url: String,
@@ -19,6 +26,131 @@ pub struct ConnectParams {
     pub request_timeout: Duration,
     #[cfg(feature = "websocket")]
     pub ws_config: Option<tokio_tungstenite::tungstenite::protocol::WebSocketConfig>,
+    /// Reconnect automatically after the connection drops. `None` stops the actor instead.
+    pub reconnect: Option<ReconnectPolicy>,
+    /// WebSocket ping/pong dead-peer detection. Ignored by non-WebSocket transports.
+    pub keepalive: Option<Keepalive>,
+    /// Bound on messages buffered ahead of the transport sink.
+    pub outbound_queue: OutboundQueue,
+    /// Tunnel the connection through an HTTP CONNECT or SOCKS5 proxy. WebSocket transport only.
+    pub proxy: Option<ProxyConfig>,
+    /// Extra roots, client certificate and validation switches for `wss://`.
+    pub tls: TlsConfig,
+    /// Extra headers for the WebSocket upgrade request (e.g. `Authorization`).
+    pub headers: HashMap<String, String>,
+    /// Query parameters appended to the URL of the upgrade request, e.g. access tokens.
+    /// Kept out of `url` so they don't end up in logs.
+    pub query_params: HashMap<String, String>,
+    /// Records every frame sent and received to a JSONL file. May be shared between connections.
+    pub recorder: Option<TrafficRecorder>,
+}
+
+impl ConnectParams {
+    /// Parameters for `url` with default timeouts and every optional feature turned off.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: Duration::from_secs(30),
+            #[cfg(feature = "websocket")]
+            ws_config: None,
+            reconnect: None,
+            keepalive: None,
+            outbound_queue: OutboundQueue::default(),
+            proxy: None,
+            tls: TlsConfig::default(),
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            recorder: None,
+        }
+    }
+}
+
+/// What `SendRawMessage` does when the outbound queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail the send with `TransportError::SendFailed`.
+    #[default]
+    Reject,
+    /// Hold the send until the queue has room. The caller's future stays pending meanwhile.
+    Wait,
+}
+
+/// Outbound queue settings for a `ConnectionActor`.
+///
+/// Messages are handed to the transport sink one at a time, so a slow peer fills this
+/// queue instead of an unbounded write buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutboundQueue {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for OutboundQueue {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: OverflowPolicy::Reject,
+        }
+    }
+}
+
+/// Ping/pong settings used to detect peers that stopped answering (e.g. half-open TCP).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keepalive {
+    pub ping_interval: Duration,
+    pub pong_timeout: Duration,
+}
+
+impl Keepalive {
+    /// Keepalive settings from the `[transport.websocket]` config section, if enabled.
+    pub fn from_config(config: &janus_common::config::WebSocketConfig) -> Option<Self> {
+        config.ping_interval_ms.map(|interval_ms| Keepalive {
+            ping_interval: Duration::from_millis(interval_ms),
+            pong_timeout: Duration::from_millis(config.pong_timeout_ms),
+        })
+    }
+}
+
+/// How a `ConnectionActor` retries after an established connection is lost.
+///
+/// The delay before attempt `n` (1-based) is `initial_backoff * multiplier^(n-1)`,
+/// capped at `max_backoff`, then spread by +/- `jitter` (a fraction of the delay).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay to wait before the given (1-based) reconnect attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // Uniform in [-1, 1); RandomState is seeded randomly per instance, which is enough here
+        let unit = std::collections::hash_map::RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        let spread = 1.0 + jitter * (unit * 2.0 - 1.0);
+
+        Duration::from_secs_f64((capped * spread).max(0.0))
+    }
 }
 
 #[async_trait]
@@ -41,6 +173,8 @@ pub enum ConnectionState {
     Idle,
     Connecting,
     Connected,
+    /// Connection was lost; waiting for or performing reconnect attempt `attempt` (1-based).
+    Reconnecting { attempt: u32 },
     Disconnecting,
     Disconnected(Option<TransportError>),
 }
@@ -51,27 +185,82 @@ pub enum ConnectionState {
 pub struct ConnectionStatusUpdate {
     pub id: ConnectionId, // Add the ID
     pub state: ConnectionState,
+    /// Messages waiting to be written, including sends held back by `OverflowPolicy::Wait`.
+    pub queue_depth: usize,
+    /// The error that caused the most recent connection loss or failed connect, if any.
+    pub last_error: Option<TransportError>,
+}
+
+
+/// Transport-independent handle to a running `ConnectionActor`.
+///
+/// `create_transport_actor` returns this so callers do not need to name the
+/// concrete `ConnectionActor<T>` type, which differs per URL scheme.
+#[derive(Debug, Clone)]
+pub struct ConnectionHandle {
+    pub id: ConnectionId,
+    pub sender: Recipient<SendRawMessage>,
+    pub closer: Recipient<CloseConnection>,
+    /// Shared with the actor, so snapshots are always current.
+    pub metrics: ConnectionMetrics,
+}
+
+impl ConnectionHandle {
+    /// Starts `actor` and returns a handle to it.
+    pub fn start<T: Transport>(actor: ConnectionActor<T>) -> Self {
+        let (id, metrics) = (actor.id, actor.metrics.clone());
+        let addr = actor.start();
+        Self { id, sender: addr.clone().recipient(), closer: addr.recipient(), metrics }
+    }
 }
 
+/// A message waiting in the outbound queue, stamped for `ConnectionMetrics::queue_wait`.
+struct Queued {
+    message: String,
+    since: Instant,
+}
+
+impl Queued {
+    fn new(message: String) -> Self {
+        Self { message, since: Instant::now() }
+    }
+}
 
 /// Actor responsible for managing a single underlying transport connection.
-// Derive WriteHandler for FramedWrite if using it
-pub struct ConnectionActor<T: Transport>
-    where <T as Transport>::Sink: ActorFrame // Require ActorFrame for FramedWrite sink
-{
+pub struct ConnectionActor<T: Transport> {
     id: ConnectionId, // Add ID field
-    // Store the write half (Sink) for sending messages
-    writer: Option<actix::io::FramedWrite<<T as Transport>::Sink, ConnectionCodec>>, // Store FramedWrite
+    /// Write half of the transport. Taken out while a send is in flight.
+    writer: Option<<T as Transport>::Sink>,
+    /// The send currently holding `writer`, cancelled if the connection drops.
+    in_flight: Option<SpawnHandle>,
+    /// Messages accepted by `SendRawMessage` but not yet handed to the sink.
+    outbound: VecDeque<Queued>,
+    /// Sends held back by `OverflowPolicy::Wait`, resolved once admitted to `outbound`.
+    blocked: VecDeque<(Queued, oneshot::Sender<Result<(), TransportError>>)>,
+    /// Set when `outbound` hit capacity, so the drain back to empty is reported.
+    saturated: bool,
     params: ConnectParams,
     state: ConnectionState,
+    last_error: Option<TransportError>,
     message_handler: Recipient<IncomingRawMessage>,
     supervisor: Option<Recipient<ConnectionStatusUpdate>>,
-    // reader_handle is removed, stream handling is integrated
+    /// The read stream of the current connection, cancelled when the connection is dropped.
+    reader: Option<SpawnHandle>,
+    /// `<Domain>.enable` commands sent at browser level, keyed by method, replayed after a reconnect.
+    enabled_domains: HashMap<String, serde_json::Value>,
+    next_replay_id: u64,
+    metrics: ConnectionMetrics,
+    /// Bumped for every established connection. Reads still queued from an earlier
+    /// (dropped) connection carry an older generation and are ignored.
+    generation: u64,
 }
 
-impl<T: Transport> ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
+/// Ids used for replayed `.enable` commands run from here up to `i32::MAX` (Chrome
+/// rejects larger ids). The command layer must keep its own ids below this so the
+/// responses are never mistaken for a pending command.
+pub const REPLAY_ID_BASE: u64 = 2_000_000_000;
+
+impl<T: Transport> ConnectionActor<T> {
     pub fn new(
         id: ConnectionId, // Receive ID
         params: ConnectParams,
@@ -81,10 +270,223 @@ impl<T: Transport> ConnectionActor<T>
         Self {
             id, // Store ID
             writer: None, // Initialize writer as None
+            in_flight: None,
+            reader: None,
+            outbound: VecDeque::new(),
+            blocked: VecDeque::new(),
+            saturated: false,
             params,
             state: ConnectionState::Idle,
+            last_error: None,
             message_handler,
             supervisor,
+            enabled_domains: HashMap::new(),
+            next_replay_id: REPLAY_ID_BASE,
+            metrics: ConnectionMetrics::new(id),
+            generation: 0,
+        }
+    }
+
+    /// Spawns a transport connect attempt. The outcome comes back as
+    /// `ConnectionEstablished` or `ConnectionLost`.
+    fn spawn_connect(&mut self, ctx: &mut Context<Self>) {
+        let params = self.params.clone();
+        let id = self.id;
+        let actor_addr = ctx.address();
+
+        let connect_future = async move {
+             log::debug!("({}) Connect future starting (ID: {})", params.url, id);
+             match T::connect(params.clone()).await {
+                Ok((stream_reader, stream_writer)) => { // Expecting read/write halves
+                    log::debug!("({}) Connection successful, sending ConnectionEstablished to actor", params.url);
+                    // Send the established stream and sink back to the actor's context
+                    if let Err(e) = actor_addr.try_send(ConnectionEstablished(stream_reader, stream_writer)) {
+                         log::error!("({}) Actor context closed before connection established message could be sent.", params.url);
+                         // Attempt to disconnect the dangling writer half, recovered from the failed send
+                         let ConnectionEstablished(_, stream_writer) = e.into_inner();
+                         Arbiter::current().spawn(async move {
+                             let _ = T::disconnect(stream_writer).await;
+                         });
+                    }
+                },
+                Err(e) => {
+                    log::error!("({}) Transport connect error: {}", params.url, e);
+                    // Report failure back to the actor context.
+                    let _ = actor_addr.try_send(ConnectionLost(Some(e))); // Use try_send as actor might be stopping
+                }
+            }
+        };
+        // Spawn the connection attempt. Completion sends message back to actor.
+        ctx.spawn(connect_future.into_actor(self));
+    }
+
+    /// Called when an established connection drops (read error, EOF or write error).
+    /// Starts reconnecting if a policy allows it, otherwise stops the actor.
+    fn on_transport_lost(&mut self, error: Option<TransportError>, ctx: &mut Context<Self>) {
+        if self.state != ConnectionState::Connected {
+            // Stale notification from a stream we already gave up on
+            log::debug!("({}) Ignoring transport loss (ID: {}) in state {:?}", self.params.url, self.id, self.state);
+            return;
+        }
+        self.clear_outbound(ctx);
+        self.metrics.connection_lost();
+        self.schedule_reconnect(1, error, ctx);
+    }
+
+    /// Queues a message for the sink, applying the overflow policy when the queue is full.
+    fn enqueue(&mut self, message: String, ctx: &mut Context<Self>) -> ResponseFuture<Result<(), TransportError>> {
+        let capacity = self.params.outbound_queue.capacity.max(1);
+        // Earlier waiters go first, so a free slot is not taken by a newer send
+        if self.outbound.len() < capacity && self.blocked.is_empty() {
+            self.outbound.push_back(Queued::new(message));
+            if self.outbound.len() == capacity && !self.saturated {
+                log::warn!("({}) Outbound queue (ID: {}) is full ({} messages).", self.params.url, self.id, capacity);
+                self.saturated = true;
+                self.report_status();
+            }
+            self.pump(ctx);
+            return Box::pin(async { Ok(()) });
+        }
+
+        match self.params.outbound_queue.overflow {
+            OverflowPolicy::Reject => {
+                log::warn!("({}) Rejecting send (ID: {}): outbound queue full.", self.params.url, self.id);
+                self.metrics.send_error();
+                Box::pin(async move {
+                    Err(TransportError::SendFailed(format!("Outbound queue full ({} messages)", capacity)))
+                })
+            }
+            OverflowPolicy::Wait => {
+                let (tx, rx) = oneshot::channel();
+                self.blocked.push_back((Queued::new(message), tx));
+                // A dropped sender means the queue was cleared before this send was admitted
+                Box::pin(async move { rx.await.unwrap_or(Err(TransportError::NotConnected)) })
+            }
+        }
+    }
+
+    /// Hands the next queued message to the sink. Only one send is in flight at a time,
+    /// so the sink's readiness is what drains the queue.
+    fn pump(&mut self, ctx: &mut Context<Self>) {
+        if self.outbound.is_empty() {
+            return;
+        }
+        let Some(mut sink) = self.writer.take() else { return }; // Not connected, or a send is in flight
+        let Some(Queued { message, since }) = self.outbound.pop_front() else { return };
+        self.admit_blocked();
+        if let Some(recorder) = &self.params.recorder {
+            recorder.record(self.id, Direction::Outbound, &message);
+        }
+        self.metrics.frame_out(&message, since);
+
+        let send = async move {
+            let result = sink.send(message).await;
+            (sink, result)
+        }
+        .into_actor(self)
+        .map(|(sink, result), act, ctx| {
+            act.in_flight = None;
+            match result {
+                Ok(()) => {
+                    act.writer = Some(sink);
+                    if act.outbound.is_empty() && act.saturated {
+                        act.saturated = false;
+                        act.report_status();
+                    }
+                    act.pump(ctx);
+                }
+                Err(e) => {
+                    log::error!("({}) Transport sink (write) error (ID: {}): {}", act.params.url, act.id, e);
+                    act.metrics.send_error();
+                    act.on_transport_lost(Some(e), ctx);
+                }
+            }
+        });
+        self.in_flight = Some(ctx.spawn(send));
+    }
+
+    /// Moves held-back sends into the freed queue slots and lets their callers continue.
+    fn admit_blocked(&mut self) {
+        let capacity = self.params.outbound_queue.capacity.max(1);
+        while self.outbound.len() < capacity {
+            let Some((message, waiter)) = self.blocked.pop_front() else { break };
+            self.outbound.push_back(message);
+            let _ = waiter.send(Ok(()));
+        }
+    }
+
+    /// Drops the reader, the writer and everything queued for it; held-back sends fail
+    /// with `NotConnected`.
+    fn clear_outbound(&mut self, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.in_flight.take() {
+            ctx.cancel_future(handle);
+        }
+        if let Some(handle) = self.reader.take() {
+            ctx.cancel_future(handle);
+        }
+        self.writer = None;
+        if !self.outbound.is_empty() || !self.blocked.is_empty() {
+            log::warn!("({}) Dropping {} queued and {} waiting messages (ID: {}).",
+                self.params.url, self.outbound.len(), self.blocked.len(), self.id);
+        }
+        self.outbound.clear();
+        for (_, waiter) in self.blocked.drain(..) {
+            let _ = waiter.send(Err(TransportError::NotConnected));
+        }
+        self.saturated = false;
+    }
+
+    /// Schedules reconnect attempt `attempt`, or gives up once the policy is exhausted.
+    fn schedule_reconnect(&mut self, attempt: u32, error: Option<TransportError>, ctx: &mut Context<Self>) {
+        if error.is_some() {
+            self.last_error = error.clone();
+        }
+        let max_attempts = self.params.reconnect.as_ref().map_or(0, |p| p.max_attempts);
+        if attempt > max_attempts {
+            if self.params.reconnect.is_some() {
+                log::error!("({}) Giving up reconnecting (ID: {}) after {} attempts.", self.params.url, self.id, attempt - 1);
+            }
+            self.update_state(ConnectionState::Disconnected(error), ctx);
+            ctx.stop();
+            return;
+        }
+
+        let delay = self.params.reconnect.as_ref().map(|p| p.backoff(attempt)).unwrap_or_default();
+        log::warn!("({}) Reconnect attempt {}/{} (ID: {}) in {:?}. Last error: {:?}",
+            self.params.url, attempt, max_attempts, self.id, delay, error);
+        self.update_state(ConnectionState::Reconnecting { attempt }, ctx);
+        ctx.run_later(delay, |act, ctx| act.spawn_connect(ctx));
+    }
+
+    /// Tracks browser-level `<Domain>.enable` / `.disable` commands so they can be replayed.
+    fn track_domain_state(&mut self, raw: &str) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(raw) else { return };
+        // Flattened sessions do not survive a new socket, so only browser-level state is kept
+        if value.get("sessionId").is_some() {
+            return;
+        }
+        let Some(method) = value.get("method").and_then(|m| m.as_str()) else { return };
+
+        if let Some(domain) = method.strip_suffix(".enable") {
+            let params = value.get("params").cloned().unwrap_or(serde_json::Value::Null);
+            log::trace!("({}) Tracking enabled domain {} (ID: {})", self.params.url, domain, self.id);
+            self.enabled_domains.insert(method.to_string(), params);
+        } else if let Some(domain) = method.strip_suffix(".disable") {
+            self.enabled_domains.remove(&format!("{}.enable", domain));
+        }
+    }
+
+    /// Queues every tracked `.enable` command for the freshly established writer.
+    /// Replays bypass the capacity check; the queue was emptied when the connection dropped.
+    fn replay_enabled_domains(&mut self) {
+        for (method, params) in &self.enabled_domains {
+            let mut command = serde_json::json!({ "id": self.next_replay_id, "method": method });
+            if !params.is_null() {
+                command["params"] = params.clone();
+            }
+            self.next_replay_id = if self.next_replay_id >= i32::MAX as u64 { REPLAY_ID_BASE } else { self.next_replay_id + 1 };
+            log::info!("({}) Re-enabling {} after reconnect (ID: {})", self.params.url, method, self.id);
+            self.outbound.push_back(Queued::new(command.to_string()));
         }
     }
 
@@ -92,28 +494,36 @@ impl<T: Transport> ConnectionActor<T>
          if self.state != new_state {
             log::info!("({}) Connection state (ID: {}) changing: {:?} -> {:?}", self.params.url, self.id, self.state, new_state);
             self.state = new_state.clone();
-            if let Some(supervisor) = &self.supervisor {
-                let update_msg = ConnectionStatusUpdate { id: self.id, state: self.state.clone() };
-                if let Err(e) = supervisor.try_send(update_msg) {
-                    log::error!("({}) Failed to send connection status update (ID: {}) to supervisor: {}", self.params.url, self.id, e);
-                }
-            }
 
-            // If disconnected, ensure the writer is cleared and context might stop
+            // If disconnected, ensure the writer and queue are cleared and context might stop
             if matches!(self.state, ConnectionState::Disconnected(_)) {
                 log::debug!("({}) Clearing writer due to disconnection (ID: {}).", self.params.url, self.id);
-                self.writer = None;
+                self.clear_outbound(ctx);
                 // Optionally stop the actor context if disconnection is fatal
                 // ctx.stop();
             }
+            self.report_status();
+        }
+    }
+
+    /// Sends the current state and queue depth to the supervisor, if any.
+    fn report_status(&self) {
+        if let Some(supervisor) = &self.supervisor {
+            let update_msg = ConnectionStatusUpdate {
+                id: self.id,
+                state: self.state.clone(),
+                queue_depth: self.outbound.len() + self.blocked.len(),
+                last_error: self.last_error.clone(),
+            };
+            if let Err(e) = supervisor.try_send(update_msg) {
+                log::error!("({}) Failed to send connection status update (ID: {}) to supervisor: {}", self.params.url, self.id, e);
+            }
         }
     }
 }
 
 
-impl<T: Transport> Actor for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
+impl<T: Transport> Actor for ConnectionActor<T> {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
@@ -123,34 +533,7 @@ impl<T: Transport> Actor for ConnectionActor<T>
              self.state = ConnectionState::Idle; // Reset state
         }
         self.update_state(ConnectionState::Connecting, ctx);
-
-        let params = self.params.clone();
-        let actor_addr = ctx.address();
-
-        // Spawn the connection attempt task
-        let connect_future = async move {
-             log::debug!("({}) Connect future starting (ID: {})", params.url, actor_addr.recipient::<ConnectionEstablished<T>>().actor_id()); // Can't easily get ID here, log URL
-             match T::connect(params.clone()).await {
-                Ok((stream_reader, stream_writer)) => { // Expecting read/write halves
-                    log::debug!("({}) Connection successful, sending ConnectionEstablished to actor", params.url);
-                    // Send the established stream and sink back to the actor's context
-                    if actor_addr.try_send(ConnectionEstablished(stream_reader, stream_writer)).is_err() {
-                         log::error!("({}) Actor context closed before connection established message could be sent.", params.url);
-                         // Attempt to disconnect the dangling writer half
-                         Arbiter::current().spawn(async move {
-                             let _ = T::disconnect(stream_writer).await;
-                         });
-                    }
-                },
-                Err(e) => {
-                    log::error!("({}) Transport connect error: {}", params.url, e);
-                    // Report failure back to the actor context.
-                    let _ = actor_addr.try_send(ConnectionLost(Some(e))); // Use try_send as actor might be stopping
-                }
-            }
-        };
-        // Spawn the connection attempt. Completion sends message back to actor.
-        ctx.spawn(connect_future.into_actor(self));
+        self.spawn_connect(ctx);
     }
 
     fn stopping(&mut self, ctx: &mut Context<Self>) -> Running {
@@ -158,10 +541,8 @@ impl<T: Transport> Actor for ConnectionActor<T>
         self.update_state(ConnectionState::Disconnecting, ctx); // Update state first
 
          // Attempt graceful disconnect using the stored writer (if any)
-         if let Some(writer) = self.writer.take() {
+         if let Some(sink) = self.writer.take() {
              log::debug!("({}) Initiating graceful disconnect of transport sink (ID: {})...", self.params.url, self.id);
-             // Get the inner Sink and disconnect it
-             let sink = writer.into_inner();
              Arbiter::current().spawn(async move {
                  match T::disconnect(sink).await {
                      Ok(_) => log::debug!("Transport disconnected successfully."),
@@ -191,28 +572,55 @@ struct ConnectionEstablished<T: Transport>(T, T::Sink); // Contains reader (Stre
 #[rtype(result = "()")]
 struct ConnectionLost(Option<TransportError>);
 
+/// Internal message for one read from the transport stream; `item` is `None` once the
+/// stream has ended. `generation` identifies the connection the stream belongs to.
+#[derive(Message)]
+#[rtype(result = "()")]
+struct TransportRead {
+    generation: u64,
+    item: Option<Result<String, TransportError>>,
+}
+
+/// Closes the connection cleanly and stops its actor. Resolves once the transport has
+/// been disconnected, so a pipe-mode browser has exited by then. Queued messages are dropped.
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "()")]
+pub struct CloseConnection;
+
 // StartReadLoop message is removed
 
 // --- Message Handlers ---
 
-impl<T: Transport> Handler<ConnectionEstablished<T>> for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
+impl<T: Transport> Handler<ConnectionEstablished<T>> for ConnectionActor<T> {
     type Result = ();
 
     fn handle(&mut self, msg: ConnectionEstablished<T>, ctx: &mut Context<Self>) {
          log::info!("({}) Connection established successfully (ID: {})", self.params.url, self.id);
-         if self.state == ConnectionState::Connecting {
+         if matches!(self.state, ConnectionState::Connecting | ConnectionState::Reconnecting { .. }) {
+            let reconnected = self.state != ConnectionState::Connecting;
             let (stream_reader, stream_writer) = (msg.0, msg.1);
 
-            // Store the writer half using FramedWrite for Sink integration
-            self.writer = Some(FramedWrite::new(stream_writer, ConnectionCodec, ctx));
+            // Store the writer half; `pump` moves queued messages into it
+            self.writer = Some(stream_writer);
+
+            // Every read, and the end of the stream, is delivered as a `TransportRead`
+            // tagged with this connection's generation
+            self.generation += 1;
+            let generation = self.generation;
+            let reads = stream_reader
+                .map(move |item| TransportRead { generation, item: Some(item) })
+                .chain(futures_util::stream::once(async move { TransportRead { generation, item: None } }));
+            if let Some(stale) = self.reader.replace(ctx.add_stream(reads)) {
+                ctx.cancel_future(stale);
+            }
 
-            // Add the reader stream to the actor context
-            // This starts processing incoming messages using the StreamHandler trait implementation
-            Self::add_stream(stream_reader, ctx);
+            if reconnected {
+                self.metrics.reconnected();
+                self.replay_enabled_domains();
+            }
 
             self.update_state(ConnectionState::Connected, ctx);
+            self.pump(ctx);
             log::info!("({}) ConnectionActor (ID: {}) is now Connected and handling stream.", self.params.url, self.id);
 
          } else {
@@ -225,112 +633,268 @@ impl<T: Transport> Handler<ConnectionEstablished<T>> for ConnectionActor<T>
 }
 
 
-// Implement StreamHandler to process messages received from the transport stream
-impl<T: Transport> StreamHandler<Result<String, TransportError>> for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
-    fn handle(&mut self, item: Result<String, TransportError>, ctx: &mut Context<Self>) {
-        match item {
-            Ok(msg) => {
+// Reads arrive through the stream added in `ConnectionEstablished`
+impl<T: Transport> StreamHandler<TransportRead> for ConnectionActor<T> {
+    fn handle(&mut self, msg: TransportRead, ctx: &mut Context<Self>) {
+        <Self as Handler<TransportRead>>::handle(self, msg, ctx);
+    }
+
+    // The end of the stream is already delivered as a `TransportRead`; the default
+    // would stop the actor.
+    fn finished(&mut self, _ctx: &mut Context<Self>) {}
+}
+
+// Processes messages received from the transport stream
+impl<T: Transport> Handler<TransportRead> for ConnectionActor<T> {
+    type Result = ();
+
+    fn handle(&mut self, msg: TransportRead, ctx: &mut Context<Self>) {
+        if msg.generation != self.generation {
+            // Left over from a connection that was dropped and has since been replaced
+            log::debug!("({}) Ignoring read from stale connection generation {} (ID: {}, current {}).",
+                self.params.url, msg.generation, self.id, self.generation);
+            return;
+        }
+        match msg.item {
+            Some(Ok(msg)) => {
                 // Forward successfully received message to the designated handler
                 log::trace!("({}) Received raw message (ID: {}), forwarding to handler.", self.params.url, self.id);
-                if let Err(e) = self.message_handler.try_send(IncomingRawMessage(msg)) {
+                if let Some(recorder) = &self.params.recorder {
+                    recorder.record(self.id, Direction::Inbound, &msg);
+                }
+                self.metrics.frame_in(&msg);
+                if let Err(e) = self.message_handler.try_send(IncomingRawMessage { connection_id: self.id, raw: msg }) {
                     log::error!("({}) Failed to send incoming message to handler (ID: {}): {}. Dropping message.", self.params.url, self.id, e);
                     // Handle backpressure or error if necessary
                 }
             }
-            Err(e) => {
+            Some(Err(e)) => {
                 log::error!("({}) Transport receive error (ID: {}): {}", self.params.url, self.id, e);
                 // Connection is considered lost on stream error
-                self.update_state(ConnectionState::Disconnected(Some(e)), ctx);
-                ctx.stop(); // Stop the actor on receive error
+                self.on_transport_lost(Some(e), ctx);
+            }
+            None => {
+                log::info!("({}) Transport stream finished (ID: {}). Connection closed by peer.", self.params.url, self.id);
+                // Stream finished means the peer closed gracefully (or unexpectedly EOF)
+                self.on_transport_lost(None, ctx);
             }
         }
     }
-
-    fn finished(&mut self, ctx: &mut Context<Self>) {
-        log::info!("({}) Transport stream finished (ID: {}). Connection closed by peer.", self.params.url, self.id);
-        // Stream finished means the peer closed gracefully (or unexpectedly EOF)
-        self.update_state(ConnectionState::Disconnected(None), ctx);
-        ctx.stop(); // Stop the actor when the stream finishes
-    }
-}
-
-// Implement WriteHandler for FramedWrite based communication
-impl<T: Transport> WriteHandler<TransportError> for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
-    fn error(&mut self, err: TransportError, _ctx: &mut Context<Self>) -> Running {
-        log::error!("({}) Transport sink (write) error (ID: {}): {}", self.params.url, self.id, err);
-        // Treat sink errors as fatal disconnection
-        self.update_state(ConnectionState::Disconnected(Some(err)), _ctx);
-        Running::Stop // Stop actor on write error
-    }
-
-    fn finished(&mut self, _ctx: &mut Context<Self>) -> Running {
-         log::debug!("({}) Transport sink finished (ID: {}), likely due to disconnect.", self.params.url, self.id);
-         // Sink finishing often means connection is closed/closing. Ensure state reflects this.
-         if !matches!(self.state, ConnectionState::Disconnected(_) | ConnectionState::Disconnecting) {
-              self.update_state(ConnectionState::Disconnected(None), _ctx);
-         }
-        Running::Stop // Stop actor if sink finishes unexpectedly
-    }
 }
 
-
- impl<T: Transport> Handler<ConnectionLost> for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
- {
+ impl<T: Transport> Handler<ConnectionLost> for ConnectionActor<T> {
     type Result = ();
 
     fn handle(&mut self, msg: ConnectionLost, ctx: &mut Context<Self>) {
          log::warn!("({}) Handling ConnectionLost signal (ID: {}). Reason: {:?}", self.params.url, self.id, msg.0);
 
-         self.writer = None; // Ensure writer is cleared
+         self.clear_outbound(ctx); // Ensure writer and queue are cleared
+         if msg.0.is_some() {
+              self.last_error = msg.0.clone();
+         }
 
-         if !matches!(self.state, ConnectionState::Disconnecting | ConnectionState::Disconnected(_)) {
-              self.update_state(ConnectionState::Disconnected(msg.0), ctx);
-         } else {
-              log::debug!("({}) Connection (ID: {}) already in state {:?}, not changing state.", self.params.url, self.id, self.state);
+         match self.state {
+              // A reconnect attempt failed; try the next one or give up.
+              // Rejected credentials will not fix themselves, so those are not retried.
+              ConnectionState::Reconnecting { attempt } if !matches!(msg.0, Some(TransportError::AuthenticationFailed { .. })) => {
+                   self.schedule_reconnect(attempt + 1, msg.0, ctx);
+                   return;
+              }
+              ConnectionState::Disconnecting | ConnectionState::Disconnected(_) => {
+                   log::debug!("({}) Connection (ID: {}) already in state {:?}, not changing state.", self.params.url, self.id, self.state);
+              }
+              _ => self.update_state(ConnectionState::Disconnected(msg.0), ctx),
          }
-         ctx.stop(); // Stop the actor when connection is lost externally (e.g., connect failed)
+         ctx.stop(); // Stop the actor when connection is lost externally (e.g., initial connect failed)
     }
 }
 
+impl<T: Transport> Handler<CloseConnection> for ConnectionActor<T> {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: CloseConnection, ctx: &mut Context<Self>) -> Self::Result {
+        log::info!("({}) Closing connection (ID: {}) on request.", self.params.url, self.id);
+        self.update_state(ConnectionState::Disconnecting, ctx); // No further sends are accepted
+        // A send in flight holds the writer; it is abandoned and the sink dropped with it
+        let sink = self.writer.take();
+        self.clear_outbound(ctx);
+
+        let url = self.params.url.clone();
+        Box::pin(
+            async move {
+                match sink {
+                    Some(sink) => T::disconnect(sink).await,
+                    None => Ok(()),
+                }
+            }
+            .into_actor(self)
+            .map(move |result, _act, ctx| {
+                if let Err(e) = result {
+                    log::warn!("({}) Error during transport disconnect: {}", url, e);
+                }
+                ctx.stop(); // `stopping` reports Disconnected
+            }),
+        )
+    }
+}
 
 // Handler for SendRawMessage (inherited from janus-core)
-impl<T: Transport> Handler<SendRawMessage> for ConnectionActor<T>
-    where <T as Transport>::Sink: ActorFrame
-{
-    // Use MessageResult for synchronous handling within actor context
-    type Result = Result<(), TransportError>;
+impl<T: Transport> Handler<SendRawMessage> for ConnectionActor<T> {
+    // A future, since `OverflowPolicy::Wait` may hold the send until the queue has room
+    type Result = ResponseFuture<Result<(), TransportError>>;
 
-    fn handle(&mut self, msg: SendRawMessage, _ctx: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: SendRawMessage, ctx: &mut Context<Self>) -> Self::Result {
         if self.state != ConnectionState::Connected {
             log::warn!("({}) Attempted to send message (ID: {}) while not connected (State: {:?})", self.params.url, self.id, self.state);
-            return Err(TransportError::NotConnected);
+            self.metrics.send_error();
+            return Box::pin(async { Err(TransportError::NotConnected) });
         }
 
-        // --- Using FramedWrite ---
-        if let Some(writer) = &mut self.writer {
-             log::trace!("({}) Sending raw message (ID: {}) via FramedWrite.", self.params.url, self.id);
-             writer.write(msg.0); // Send the message using the FramedWrite
-             Ok(())
-        } else {
-             log::error!("({}) Internal state inconsistency (ID: {}): State is Connected but writer is None.", self.params.url, self.id);
-             Err(TransportError::Internal("Transport writer missing in Connected state".to_string()))
+        // Remember enabled domains so a reconnect can restore them
+        if self.params.reconnect.is_some() {
+             self.track_domain_state(&msg.0);
         }
+
+        log::trace!("({}) Queueing raw message (ID: {}), depth {}.", self.params.url, self.id, self.outbound.len());
+        self.enqueue(msg.0, ctx)
     }
 }
 
 // --- Codec for FramedWrite ---
-// This assumes text-based protocols like WebSocket JSON messages.
-// Adjust if binary framing is needed.
+// Message-oriented transports (WebSocket) use `Framing::Passthrough`; byte-stream
+// transports pick the framing their protocol defines.
+#[cfg(feature = "tcp")]
+use base64::Engine;
+#[cfg(feature = "tcp")]
+use bytes::Buf;
 use bytes::{BytesMut, BufMut};
 use tokio_util::codec::{Encoder, Decoder};
 
-#[derive(Default)]
-pub struct ConnectionCodec;
+/// Longest `<length>:` or `bulk <actor> <type> <length>:` header accepted before
+/// the stream is considered corrupt.
+#[cfg(feature = "tcp")]
+const MAX_PACKET_HEADER_LEN: usize = 1024;
+
+/// Largest packet or delimited message accepted by default, the same as the default
+/// `[transport.websocket] max_message_size`.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How messages are delimited on the underlying byte stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// The transport already delivers whole messages; the buffer is one message.
+    #[default]
+    Passthrough,
+    /// Each message is terminated by the given byte (Chrome's `--remote-debugging-pipe` uses NUL).
+    Delimited(u8),
+    /// Firefox Remote Debugging Protocol: `<byte length>:<json>`, plus
+    /// `bulk <actor> <type> <byte length>:<raw bytes>` packets.
+    #[cfg(feature = "tcp")]
+    LengthPrefixed,
+}
+
+#[derive(Debug)]
+pub struct ConnectionCodec {
+    framing: Framing,
+    /// Packets announcing a longer body are rejected before anything is buffered, and
+    /// delimited messages once this many bytes arrived without a delimiter.
+    max_message_size: usize,
+}
+
+impl Default for ConnectionCodec {
+    fn default() -> Self {
+        Self::new(Framing::default())
+    }
+}
+
+impl ConnectionCodec {
+    pub fn new(framing: Framing) -> Self {
+        Self { framing, max_message_size: DEFAULT_MAX_MESSAGE_SIZE }
+    }
+
+    /// Overrides the largest packet or delimited message accepted. `None` keeps the default.
+    pub fn with_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        if let Some(max_message_size) = max_message_size {
+            self.max_message_size = max_message_size;
+        }
+        self
+    }
+
+    /// Codec for Chrome's pipe mode: JSON messages terminated by `\0`.
+    pub fn nul_delimited() -> Self {
+        Self::new(Framing::Delimited(b'\0'))
+    }
+
+    /// Codec for Firefox's RDP stream framing.
+    #[cfg(feature = "tcp")]
+    pub fn length_prefixed() -> Self {
+        Self::new(Framing::LengthPrefixed)
+    }
+}
+
+#[cfg(feature = "tcp")]
+fn parse_packet_length(raw: &str) -> Result<usize, TransportError> {
+    raw.parse().map_err(|_| TransportError::Serde(format!("Invalid packet length '{}'", raw)))
+}
+
+/// Decodes one RDP packet. Bulk packets are surfaced as a JSON envelope
+/// `{"actor", "type", "bulk": true, "length", "data"}` with base64 `data`, so
+/// they can travel through the same `String` stream as JSON packets.
+#[cfg(feature = "tcp")]
+fn decode_length_prefixed(src: &mut BytesMut, max_message_size: usize) -> Result<Option<String>, TransportError> {
+    let Some(colon) = src.iter().take(MAX_PACKET_HEADER_LEN).position(|b| *b == b':') else {
+        if src.len() >= MAX_PACKET_HEADER_LEN {
+            return Err(TransportError::Serde("Packet header too long".to_string()));
+        }
+        return Ok(None);
+    };
+    let header = decode_utf8(&src[..colon])?;
+
+    let (bulk, length) = match header.strip_prefix("bulk ") {
+        Some(rest) => {
+            let parts: Vec<&str> = rest.split(' ').collect();
+            let [actor, kind, length] = parts[..] else {
+                return Err(TransportError::Serde(format!("Malformed bulk header '{}'", header)));
+            };
+            (Some((actor.to_string(), kind.to_string())), parse_packet_length(length)?)
+        }
+        None => (None, parse_packet_length(&header)?),
+    };
+    // Checked before reserving, so a corrupt or hostile header can't make us allocate it
+    if length > max_message_size {
+        return Err(TransportError::Serde(format!(
+            "Packet length {} exceeds the {} byte limit",
+            length, max_message_size
+        )));
+    }
+
+    if src.len() < colon + 1 + length {
+        src.reserve(colon + 1 + length - src.len());
+        return Ok(None);
+    }
+    src.advance(colon + 1);
+    let body = src.split_to(length);
+
+    match bulk {
+        None => decode_utf8(&body).map(Some),
+        Some((actor, kind)) => Ok(Some(serde_json::json!({
+            "actor": actor,
+            "type": kind,
+            "bulk": true,
+            "length": length,
+            "data": base64::engine::general_purpose::STANDARD.encode(&body),
+        }).to_string())),
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Result<String, TransportError> {
+    std::str::from_utf8(bytes)
+        .map(|s| s.to_owned())
+        .map_err(|e| {
+            log::error!("Codec UTF-8 decoding error: {}", e);
+            TransportError::Serde(format!("Invalid UTF-8 sequence: {}", e))
+        })
+}
 
 // Implement Decoder to handle incoming byte streams -> String messages
 impl Decoder for ConnectionCodec {
@@ -338,27 +902,34 @@ impl Decoder for ConnectionCodec {
     type Error = TransportError; // Use our TransportError
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // This is a simple example assuming UTF-8 strings delimited somehow
-        // For WebSocket, tungstenite handles framing; this codec might be simpler
-        // or even unnecessary if the Sink/Stream directly handle String.
-        // Assuming the Transport::Sink/Stream deals with String directly, this might not be needed.
-        // If the underlying transport gives raw bytes, implement framing logic here.
-
-        // Simplistic example: Treat entire buffer as one message (adjust!)
-        if src.is_empty() {
-            Ok(None)
-        } else {
-            match std::str::from_utf8(src) {
-                 Ok(s) => {
-                     let s_owned = s.to_owned();
-                     src.clear(); // Consume buffer
-                     Ok(Some(s_owned))
-                 },
-                 Err(e) => {
-                      log::error!("Codec UTF-8 decoding error: {}", e);
-                      Err(TransportError::Serde(format!("Invalid UTF-8 sequence: {}", e)))
-                 }
+        match self.framing {
+            Framing::Passthrough => {
+                if src.is_empty() {
+                    return Ok(None);
+                }
+                let message = decode_utf8(src)?;
+                src.clear(); // Consume buffer
+                Ok(Some(message))
+            }
+            Framing::Delimited(delimiter) => {
+                // Wait for more bytes until a full message is buffered, but not forever:
+                // a peer that never sends the delimiter would grow the buffer without bound
+                let end = src.iter().position(|b| *b == delimiter);
+                let length = end.unwrap_or(src.len());
+                if length > self.max_message_size {
+                    return Err(TransportError::Serde(format!(
+                        "Message of {}{} bytes exceeds the {} byte limit",
+                        if end.is_some() { "" } else { "at least " }, length, self.max_message_size
+                    )));
+                }
+                let Some(end) = end else {
+                    return Ok(None);
+                };
+                let frame = src.split_to(end + 1);
+                decode_utf8(&frame[..end]).map(Some)
             }
+            #[cfg(feature = "tcp")]
+            Framing::LengthPrefixed => decode_length_prefixed(src, self.max_message_size),
         }
     }
 }
@@ -368,64 +939,341 @@ impl Encoder<String> for ConnectionCodec {
     type Error = TransportError;
 
     fn encode(&mut self, item: String, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        // Reserve space and write the string bytes
-        dst.reserve(item.len());
-        dst.put(item.as_bytes());
+        match self.framing {
+            Framing::Passthrough => {
+                // Reserve space and write the string bytes
+                dst.reserve(item.len());
+                dst.put(item.as_bytes());
+            }
+            Framing::Delimited(delimiter) => {
+                if item.as_bytes().contains(&delimiter) {
+                    return Err(TransportError::Serde("Message contains the frame delimiter".to_string()));
+                }
+                dst.reserve(item.len() + 1);
+                dst.put(item.as_bytes());
+                dst.put_u8(delimiter);
+            }
+            #[cfg(feature = "tcp")]
+            Framing::LengthPrefixed => {
+                // Outgoing packets are always JSON; the client never sends bulk data
+                let header = format!("{}:", item.len());
+                dst.reserve(header.len() + item.len());
+                dst.put(header.as_bytes());
+                dst.put(item.as_bytes());
+            }
+        }
         Ok(())
     }
 }
 
-// --- ActorFrame for Sink ---
-// We need to tell actix how to handle frames with our chosen Sink type.
-// This might require a wrapper around the Sink if it doesn't implement ActorFrame directly.
-// If Transport::Sink is e.g., SplitSink<WebSocketStream<...>, WsMessage>, we need to handle that.
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// Placeholder/Example: This might need significant adjustment based on the actual Sink type
-// returned by the Transport implementation (e.g., WebSocketTransport).
-use actix::io::ActorFrame;
-impl<S> ActorFrame for S where S: futures_util::sink::Sink<String, Error = TransportError> + Send + Unpin + 'static {}
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_length_prefixed_codec_handles_partial_and_bulk_packets() {
+        let mut codec = ConnectionCodec::length_prefixed();
+        let mut buf = BytesMut::from(&b"15:{\"from\":\"ro"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
 
-// Ensure WebSocketTransport::Sink implements ActorFrame if FramedWrite is used directly on it.
-// This likely involves changes in websocket.rs to return a Sink that works with FramedWrite,
-// or using a different approach than FramedWrite if the Sink isn't suitable.
-// For WebSockets, often you don't need FramedWrite because the Sink directly takes WsMessage::Text(String).
-// Consider simplifying by NOT using FramedWrite if the Sink already handles String messages.
+        buf.extend_from_slice(b"ot\"}bulk conn0.actor1 heap 3:\x00\x01\x02");
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some(r#"{"from":"root"}"#));
 
-// --- Simplified SendRawMessage without FramedWrite ---
-/*
-impl<T: Transport> Handler<SendRawMessage> for ConnectionActor<T> {
-    type Result = ResponseFuture<Result<(), TransportError>>;
+        let bulk: serde_json::Value = serde_json::from_str(&codec.decode(&mut buf).unwrap().unwrap()).unwrap();
+        assert_eq!(bulk["actor"], "conn0.actor1");
+        assert_eq!(bulk["type"], "heap");
+        assert_eq!(bulk["data"], "AAEC");
+        assert!(buf.is_empty());
 
-    fn handle(&mut self, msg: SendRawMessage, _ctx: &mut Context<Self>) -> Self::Result {
-        if self.state != ConnectionState::Connected {
-             log::warn!("({}) Attempted to send message (ID: {}) while not connected (State: {:?})", self.params.url, self.id, self.state);
-             return Box::pin(async { Err(TransportError::NotConnected) });
+        let mut out = BytesMut::new();
+        codec.encode(r#"{"to":"root"}"#.to_string(), &mut out).unwrap();
+        assert_eq!(&out[..], b"13:{\"to\":\"root\"}");
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_length_prefixed_codec_rejects_oversized_packets() {
+        let mut codec = ConnectionCodec::length_prefixed().with_max_message_size(Some(16));
+        let mut buf = BytesMut::from(&b"16:{\"a\":\"01234567\"}"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some(r#"{"a":"01234567"}"#));
+
+        // Rejected from the header alone, without waiting for (or buffering) the body
+        for packet in [&b"17:{"[..], &b"bulk conn0.actor1 heap 4294967296:"[..]] {
+            let mut buf = BytesMut::from(packet);
+            match codec.decode(&mut buf) {
+                Err(TransportError::Serde(detail)) => assert!(detail.contains("exceeds the 16 byte limit"), "{}", detail),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(buf.capacity() < 1024);
+        }
+    }
+
+    #[test]
+    fn test_nul_delimited_codec_splits_messages() {
+        let mut codec = ConnectionCodec::nul_delimited();
+        let mut buf = BytesMut::from(&b"{\"id\":1}\0{\"id\":2}\0{\"id\""[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some(r#"{"id":1}"#));
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some(r#"{"id":2}"#));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_nul_delimited_codec_rejects_oversized_messages() {
+        let mut codec = ConnectionCodec::nul_delimited().with_max_message_size(Some(8));
+        let mut buf = BytesMut::from(&b"{\"id\":1}\0{\"id\":"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some(r#"{"id":1}"#));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        // No delimiter within the limit: the peer is not going to end this message
+        buf.extend_from_slice(b"123");
+        match codec.decode(&mut buf) {
+            Err(TransportError::Serde(detail)) => assert!(detail.contains("exceeds the 8 byte limit"), "{}", detail),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let mut buf = BytesMut::from(&b"{\"id\":123}\0"[..]);
+        assert!(matches!(codec.decode(&mut buf), Err(TransportError::Serde(_))));
+    }
+
+    /// Transport whose sink never becomes ready, like a peer that stopped reading.
+    struct StalledTransport;
+    struct StalledSink;
+
+    impl futures_util::Stream for StalledTransport {
+        type Item = Result<String, TransportError>;
+
+        fn poll_next(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+            std::task::Poll::Pending
+        }
+    }
+
+    impl futures_util::sink::Sink<String> for StalledSink {
+        type Error = TransportError;
+
+        fn poll_ready(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Pending
+        }
+
+        fn start_send(self: std::pin::Pin<&mut Self>, _item: String) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn poll_flush(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Pending
+        }
+
+        fn poll_close(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[async_trait]
+    impl Transport for StalledTransport {
+        type Sink = StalledSink;
+
+        async fn connect(_params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
+            Ok((StalledTransport, StalledSink))
+        }
+
+        async fn disconnect(_sink: Self::Sink) -> Result<(), TransportError> {
+            Ok(())
+        }
+    }
+
+    struct NullHandler;
+
+    impl Actor for NullHandler {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<IncomingRawMessage> for NullHandler {
+        type Result = ();
+
+        fn handle(&mut self, _msg: IncomingRawMessage, _ctx: &mut Context<Self>) {}
+    }
+
+    fn stalled_connection(overflow: OverflowPolicy) -> Addr<ConnectionActor<StalledTransport>> {
+        let params = ConnectParams {
+            connect_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(1),
+            outbound_queue: OutboundQueue { capacity: 2, overflow },
+            ..ConnectParams::new("stalled://test")
+        };
+        ConnectionActor::<StalledTransport>::new(1, params, NullHandler.start().recipient(), None).start()
+    }
+
+    /// Sends the first message once the actor has finished connecting.
+    async fn send_when_connected(addr: &Addr<ConnectionActor<StalledTransport>>, message: &str) {
+        for _ in 0..50 {
+            match addr.send(SendRawMessage(message.to_string())).await.unwrap() {
+                Err(TransportError::NotConnected) => tokio::time::sleep(Duration::from_millis(10)).await,
+                result => return result.unwrap(),
+            }
+        }
+        panic!("connection never became ready");
+    }
+
+    #[actix::test]
+    async fn test_outbound_queue_applies_overflow_policy() {
+        let addr = stalled_connection(OverflowPolicy::Reject);
+        // The first message is stuck in the stalled sink; the next two fill the queue
+        send_when_connected(&addr, "1").await;
+        addr.send(SendRawMessage("2".to_string())).await.unwrap().unwrap();
+        addr.send(SendRawMessage("3".to_string())).await.unwrap().unwrap();
+        let overflow = addr.send(SendRawMessage("4".to_string())).await.unwrap();
+        assert!(matches!(overflow, Err(TransportError::SendFailed(_))), "unexpected result: {:?}", overflow);
+
+        let addr = stalled_connection(OverflowPolicy::Wait);
+        send_when_connected(&addr, "1").await;
+        addr.send(SendRawMessage("2".to_string())).await.unwrap().unwrap();
+        addr.send(SendRawMessage("3".to_string())).await.unwrap().unwrap();
+        let held = tokio::time::timeout(Duration::from_millis(100), addr.send(SendRawMessage("4".to_string()))).await;
+        assert!(held.is_err(), "send should wait while the queue is full");
+    }
+
+    #[test]
+    fn test_reconnect_backoff_grows_caps_and_jitters() {
+        let policy = ReconnectPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_secs(1));
+
+        let policy = ReconnectPolicy { jitter: 0.2, ..policy };
+        let delays: Vec<Duration> = (0..50).map(|_| policy.backoff(3)).collect();
+        assert!(delays.iter().all(|d| (Duration::from_millis(320)..=Duration::from_millis(480)).contains(d)), "{:?}", delays);
+        assert!(delays.iter().any(|d| *d != delays[0]), "jitter should spread the delays");
+        assert!(policy.backoff(10) <= Duration::from_millis(1200));
+    }
+
+    /// Collects the states reported by a `ConnectionActor`.
+    #[derive(Default)]
+    struct StatusLog(std::sync::Arc<std::sync::Mutex<Vec<ConnectionState>>>);
+
+    impl Actor for StatusLog {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<ConnectionStatusUpdate> for StatusLog {
+        type Result = ();
+
+        fn handle(&mut self, msg: ConnectionStatusUpdate, _ctx: &mut Context<Self>) {
+            self.0.lock().unwrap().push(msg.state);
+        }
+    }
+
+    #[actix::test]
+    async fn test_reads_from_replaced_connection_are_ignored() {
+        let states = StatusLog::default();
+        let log = states.0.clone();
+        let params = ConnectParams {
+            reconnect: Some(ReconnectPolicy { initial_backoff: Duration::from_millis(10), jitter: 0.0, ..Default::default() }),
+            ..ConnectParams::new("stalled://test")
+        };
+        let addr = ConnectionActor::<StalledTransport>::new(1, params, NullHandler.start().recipient(), Some(states.start().recipient())).start();
+        let connected_count = |count: usize| {
+            let log = log.clone();
+            async move {
+                for _ in 0..50 {
+                    if log.lock().unwrap().iter().filter(|s| **s == ConnectionState::Connected).count() >= count {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                panic!("not connected {} times: {:?}", count, log.lock().unwrap());
+            }
+        };
+        connected_count(1).await;
+
+        // The first connection drops and is replaced by a second one
+        addr.send(TransportRead { generation: 1, item: Some(Err(TransportError::ReceiveFailed("reset".to_string()))) }).await.unwrap();
+        connected_count(2).await;
+
+        // Its stream ending late must not take down the new connection
+        let reconnects = || log.lock().unwrap().iter().filter(|s| matches!(s, ConnectionState::Reconnecting { .. })).count();
+        addr.send(TransportRead { generation: 1, item: None }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(reconnects(), 1, "{:?}", log.lock().unwrap());
+        assert_eq!(log.lock().unwrap().last(), Some(&ConnectionState::Connected));
+
+        addr.send(TransportRead { generation: 2, item: None }).await.unwrap();
+        connected_count(3).await;
+        assert_eq!(reconnects(), 2, "{:?}", log.lock().unwrap());
+    }
+
+    /// Read halves of `TrackedTransport` connections that have not been dropped yet.
+    static LIVE_READERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    /// Like `StalledTransport`, but counts its live read halves.
+    struct TrackedTransport;
+
+    impl Drop for TrackedTransport {
+        fn drop(&mut self) {
+            LIVE_READERS.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
         }
+    }
+
+    impl futures_util::Stream for TrackedTransport {
+        type Item = Result<String, TransportError>;
+
+        fn poll_next(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+            std::task::Poll::Pending
+        }
+    }
 
-        if let Some(writer) = &mut self.writer { // Assuming self.writer stores the Sink directly
-             log::trace!("({}) Sending raw message (ID: {}) via Sink.", self.params.url, self.id);
-             // Need mutable access to writer, might require taking it or careful borrowing
-             // This requires the handler return type to be ResponseFuture again.
-             // Let's assume we store writer: Arc<Mutex<T::Sink>> or similar shared approach,
-             // or pass send capability differently.
+    #[async_trait]
+    impl Transport for TrackedTransport {
+        type Sink = StalledSink;
 
-             // --- Alternative: Store Addr<WriteActor> ---
-             // If sending is complex, delegate to a separate WriteActor that owns the Sink.
-             // self.writer.do_send(SendMessage(msg.0)) ...
+        async fn connect(_params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
+            LIVE_READERS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok((TrackedTransport, StalledSink))
+        }
 
-             // --- Direct Sink usage (requires careful state management / ResponseFuture) ---
-             // This example assumes we can get mutable access temporarily.
-             // It's often easier to use FramedWrite or a dedicated writer actor.
-             let writer_ref = self.writer.as_mut().expect("Writer missing in connected state"); // Needs mutable borrow
-             Box::pin(async move {
-                 use futures_util::sink::SinkExt;
-                 writer_ref.send(msg.0).await // Use SinkExt::send
-             })
+        async fn disconnect(_sink: Self::Sink) -> Result<(), TransportError> {
+            Ok(())
+        }
+    }
+
+    #[actix::test]
+    async fn test_read_streams_of_dropped_connections_are_released() {
+        let states = StatusLog::default();
+        let log = states.0.clone();
+        let params = ConnectParams {
+            reconnect: Some(ReconnectPolicy { initial_backoff: Duration::from_millis(10), jitter: 0.0, ..Default::default() }),
+            ..ConnectParams::new("tracked://test")
+        };
+        let addr = ConnectionActor::<TrackedTransport>::new(1, params, NullHandler.start().recipient(), Some(states.start().recipient())).start();
+        let live = || LIVE_READERS.load(std::sync::atomic::Ordering::SeqCst);
+        let connected_count = |count: usize| {
+            let log = log.clone();
+            async move {
+                for _ in 0..50 {
+                    if log.lock().unwrap().iter().filter(|s| **s == ConnectionState::Connected).count() >= count {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                panic!("not connected {} times: {:?}", count, log.lock().unwrap());
+            }
+        };
 
-        } else {
-             log::error!("({}) Internal state inconsistency (ID: {}): State is Connected but writer is None.", self.params.url, self.id);
-             Box::pin(async { Err(TransportError::Internal("Transport writer missing in Connected state".to_string())) })
+        // Each drop is reported through the stream's own generation; the stream itself never ends
+        for generation in 1..=3 {
+            connected_count(generation as usize).await;
+            assert_eq!(live(), 1);
+            addr.send(TransportRead { generation, item: Some(Err(TransportError::ReceiveFailed("reset".to_string()))) }).await.unwrap();
         }
+        connected_count(4).await;
+        assert_eq!(live(), 1);
+
+        addr.send(CloseConnection).await.unwrap();
+        assert_eq!(live(), 0);
     }
 }
-*/
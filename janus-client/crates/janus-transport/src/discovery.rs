@@ -0,0 +1,266 @@
+//! Client for Chrome's HTTP discovery endpoints (`http://host:9222/json/...`).
+//!
+//! Chrome's DevTools HTTP server only speaks plain HTTP/1.1 with `Content-Length` bodies,
+//! so a minimal request/response exchange over a `TcpStream` is all that's needed here.
+
+use crate::proxy::{connect_via_proxy, ProxyConfig};
+use janus_common::error::TransportError;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use url::Url;
+
+/// Port Chrome's DevTools HTTP server listens on unless told otherwise.
+pub const DEFAULT_DISCOVERY_PORT: u16 = 9222;
+
+/// A target as described by `/json/list` and `/json/new`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetDescriptor {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub target_type: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub web_socket_debugger_url: Option<String>,
+    pub devtools_frontend_url: Option<String>,
+    pub favicon_url: Option<String>,
+}
+
+/// Browser information as described by `/json/version`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BrowserVersion {
+    #[serde(rename = "Browser")]
+    pub browser: String,
+    #[serde(rename = "Protocol-Version")]
+    pub protocol_version: String,
+    #[serde(rename = "User-Agent", default)]
+    pub user_agent: String,
+    #[serde(rename = "V8-Version", default)]
+    pub v8_version: String,
+    #[serde(rename = "WebKit-Version", default)]
+    pub webkit_version: String,
+    #[serde(rename = "webSocketDebuggerUrl")]
+    pub web_socket_debugger_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscoveryClient {
+    host: String,
+    port: u16,
+    request_timeout: Duration,
+    proxy: Option<ProxyConfig>,
+}
+
+impl DiscoveryClient {
+    /// Creates a client for an `http://host[:port]` endpoint.
+    pub fn new(endpoint: &str, request_timeout: Duration) -> Result<Self, TransportError> {
+        let url = Url::parse(endpoint).map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        if url.scheme() != "http" {
+            return Err(TransportError::UnsupportedScheme(format!("{} (discovery needs http)", url.scheme())));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| TransportError::InvalidUrl(format!("No host in {}", endpoint)))?
+            .to_string();
+
+        Ok(DiscoveryClient {
+            host,
+            port: url.port().unwrap_or(DEFAULT_DISCOVERY_PORT),
+            request_timeout,
+            proxy: None,
+        })
+    }
+
+    /// Sends discovery requests through `proxy`, if given.
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// `GET /json/version`.
+    pub async fn version(&self) -> Result<BrowserVersion, TransportError> {
+        let body = self.request("GET", "/json/version").await?;
+        parse_body(&body)
+    }
+
+    /// Resolves the browser-level WebSocket URL via `/json/version`.
+    ///
+    /// The host and port are rewritten to the ones we reached the HTTP server on, since
+    /// Chrome reports its own listen address, which is wrong behind port forwarding.
+    pub async fn browser_ws_url(&self) -> Result<String, TransportError> {
+        let version = self.version().await?;
+        let mut url = Url::parse(&version.web_socket_debugger_url)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        url.set_host(Some(&self.host))
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        url.set_port(Some(self.port))
+            .map_err(|_| TransportError::InvalidUrl(format!("Cannot set port on {}", url)))?;
+        Ok(url.to_string())
+    }
+
+    /// `GET /json/list`.
+    pub async fn list(&self) -> Result<Vec<TargetDescriptor>, TransportError> {
+        let body = self.request("GET", "/json/list").await?;
+        parse_body(&body)
+    }
+
+    /// `PUT /json/new[?url]`. Chrome rejects `GET` on this endpoint since M111.
+    pub async fn new_target(&self, url: Option<&str>) -> Result<TargetDescriptor, TransportError> {
+        let path = match url {
+            Some(url) => format!("/json/new?{}", encode_query(url)),
+            None => "/json/new".to_string(),
+        };
+        let body = self.request("PUT", &path).await?;
+        parse_body(&body)
+    }
+
+    /// `GET /json/activate/{id}`.
+    pub async fn activate(&self, target_id: &str) -> Result<(), TransportError> {
+        self.request("GET", &format!("/json/activate/{}", target_id)).await.map(|_| ())
+    }
+
+    /// `GET /json/close/{id}`.
+    pub async fn close(&self, target_id: &str) -> Result<(), TransportError> {
+        self.request("GET", &format!("/json/close/{}", target_id)).await.map(|_| ())
+    }
+
+    async fn request(&self, method: &str, path: &str) -> Result<String, TransportError> {
+        match timeout(self.request_timeout, self.exchange(method, path)).await {
+            Ok(result) => result,
+            Err(_) => Err(TransportError::Timeout(format!(
+                "{} {} timed out after {:?}",
+                method, path, self.request_timeout
+            ))),
+        }
+    }
+
+    async fn exchange(&self, method: &str, path: &str) -> Result<String, TransportError> {
+        log::debug!("Discovery request: {} http://{}:{}{}", method, self.host, self.port, path);
+        let mut stream = match &self.proxy {
+            Some(proxy) => connect_via_proxy(proxy, &self.host, self.port).await?,
+            None => TcpStream::connect((self.host.as_str(), self.port))
+                .await
+                .map_err(|e| TransportError::ConnectionFailed(format!("{}:{}: {}", self.host, self.port, e)))?,
+        };
+
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path, self.host, self.port
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        // `Connection: close` means the body runs to EOF
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await?;
+        let raw = String::from_utf8(raw).map_err(|e| TransportError::Serde(e.to_string()))?;
+
+        let (head, body) = raw
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| TransportError::ReceiveFailed("Malformed HTTP response".to_string()))?;
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| TransportError::ReceiveFailed("Malformed HTTP status line".to_string()))?;
+
+        if !(200..300).contains(&status) {
+            return Err(TransportError::ConnectionFailed(format!(
+                "{} {} returned HTTP {}: {}",
+                method, path, status, body.trim()
+            )));
+        }
+        Ok(body.to_string())
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, so a URL passed
+/// as the `/json/new` query keeps its own `?`, `&` and `#` (Chrome unescapes it whole).
+fn encode_query(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => char::from(byte).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, TransportError> {
+    serde_json::from_str(body).map_err(|e| TransportError::Serde(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Serves one canned response per connection and returns each request line.
+    async fn stand_in(responses: Vec<&'static str>) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut request_lines = Vec::new();
+            for body in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                request_lines.push(request.lines().next().unwrap_or_default().to_string());
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            request_lines
+        });
+        (port, server)
+    }
+
+    #[tokio::test]
+    async fn resolves_browser_url_and_lists_targets() {
+        let (port, server) = stand_in(vec![
+            r#"{"Browser":"Chrome/120.0","Protocol-Version":"1.3","webSocketDebuggerUrl":"ws://localhost:9222/devtools/browser/abc"}"#,
+            r#"[{"id":"T1","type":"page","title":"Blank","url":"about:blank","webSocketDebuggerUrl":"ws://localhost:9222/devtools/page/T1"}]"#,
+            r#"{"id":"T2","type":"page","url":"https://example.com/?q=a b&x=1#top"}"#,
+        ])
+        .await;
+
+        let client = DiscoveryClient::new(&format!("http://127.0.0.1:{}", port), Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            client.browser_ws_url().await.unwrap(),
+            format!("ws://127.0.0.1:{}/devtools/browser/abc", port)
+        );
+
+        let targets = client.list().await.unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].id, "T1");
+        assert_eq!(targets[0].target_type, "page");
+
+        let created = client.new_target(Some("https://example.com/?q=a b&x=1#top")).await.unwrap();
+        assert_eq!(created.id, "T2");
+
+        assert_eq!(
+            server.await.unwrap(),
+            vec![
+                "GET /json/version HTTP/1.1",
+                "GET /json/list HTTP/1.1",
+                "PUT /json/new?https%3A%2F%2Fexample.com%2F%3Fq%3Da%20b%26x%3D1%23top HTTP/1.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_non_http_endpoints_and_defaults_the_port() {
+        let client = DiscoveryClient::new("ws://127.0.0.1:9222/devtools/browser/abc", Duration::from_secs(5));
+        assert!(matches!(client, Err(TransportError::UnsupportedScheme(_))), "{:?}", client);
+        assert_eq!(DiscoveryClient::new("http://127.0.0.1", Duration::from_secs(5)).unwrap().port, DEFAULT_DISCOVERY_PORT);
+    }
+}
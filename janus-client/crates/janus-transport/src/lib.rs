@@ -1,8 +1,6 @@
 use actix::prelude::*;
-use crate::connection::{ConnectParams, ConnectionActor, ConnectionState, ConnectionStatusUpdate, Transport, ConnectionId};
-use crate::websocket::WebSocketTransport; // Assuming WebSocketTransport is the primary one for now
-use janus_core::actor::IncomingRawMessage;
-use janus_core::error::TransportError;
+use janus_common::message::IncomingRawMessage;
+use janus_common::error::TransportError;
 
 
 // Make connection types public
@@ -10,13 +8,38 @@ pub mod connection;
 // Make specific transport implementations public if needed directly,
 // otherwise they might just be used internally via ConnectionActor setup.
 pub mod websocket;
-// pub mod tcp; // Future support
-// pub mod ipc; // Future support
+// Chrome's /json HTTP endpoints, used to resolve http:// URLs to the browser WebSocket
+pub mod discovery;
+// HTTP CONNECT / SOCKS5 tunnelling for transports that dial TCP
+pub mod proxy;
+// Custom TLS connectors for wss://
+pub mod tls;
+// JSONL recording of every frame, for debugging protocol exchanges
+pub mod recorder;
+// Plays recordings back as a transport, for tests without a browser
+pub mod replay;
+// Per-connection counters and command latency histograms
+pub mod metrics;
+// Chrome's --remote-debugging-pipe mode (fd 3/4), unix only
+#[cfg(unix)]
+pub mod pipe;
+// Firefox RDP over raw TCP
+#[cfg(feature = "tcp")]
+pub mod tcp;
 
 // Re-export key types from connection module
-pub use connection::{ConnectParams, ConnectionActor, ConnectionState, ConnectionStatusUpdate, Transport, ConnectionId};
+pub use connection::{CloseConnection, ConnectParams, ConnectionActor, ConnectionCodec, ConnectionHandle, ConnectionState, ConnectionStatusUpdate, Framing, Keepalive, OutboundQueue, OverflowPolicy, Transport, ConnectionId, ReconnectPolicy, DEFAULT_CONNECT_TIMEOUT, REPLAY_ID_BASE};
 // Re-export specific transport types if they need to be instantiated directly by users
 pub use websocket::WebSocketTransport;
+pub use discovery::{BrowserVersion, DiscoveryClient, TargetDescriptor};
+pub use proxy::{ProxyConfig, ProxyCredentials};
+pub use recorder::{Direction, TrafficRecorder};
+pub use replay::{ReplaySink, ReplayTiming, ReplayTransport};
+pub use metrics::{ConnectionMetrics, ConnectionMetricsSnapshot, LatencyHistogram};
+#[cfg(unix)]
+pub use pipe::PipeTransport;
+#[cfg(feature = "tcp")]
+pub use tcp::TcpTransport;
 
 
 /// Creates and starts the appropriate ConnectionActor based on the URL scheme.
@@ -32,17 +55,19 @@ pub use websocket::WebSocketTransport;
 ///
 /// # Returns
 ///
-/// A `Result` containing the `Addr` of the started `ConnectionActor` specialized for the
-/// determined transport protocol (e.g., `WebSocketTransport`), or a `TransportError`
-/// if the URL scheme is unsupported or invalid.
+/// A `Result` containing a `ConnectionHandle` for the started `ConnectionActor`, whose
+/// transport is picked from the URL scheme (`ws`/`wss` -> `WebSocketTransport`, `http` ->
+/// `WebSocketTransport` after resolving the browser URL via `/json/version`,
+/// `pipe` -> `PipeTransport`, `tcp` -> `TcpTransport`, `replay` -> `ReplayTransport`), or a
+/// `TransportError` if the scheme is unsupported or invalid.
 ///
 /// # Example
 ///
 /// ```no_run
 /// # use actix::prelude::*;
 /// # use std::time::Duration;
-/// # use janus_transport::{create_transport_actor, ConnectParams, ConnectionId};
-/// # use janus_core::actor::IncomingRawMessage;
+/// # use janus_transport::{create_transport_actor, ConnectParams, ConnectionId, Keepalive, OutboundQueue, OverflowPolicy, ReconnectPolicy};
+/// # use janus_common::message::IncomingRawMessage;
 /// #
 /// # #[derive(Message)]
 /// # #[rtype(result = "()")]
@@ -50,13 +75,13 @@ pub use websocket::WebSocketTransport;
 /// # impl Handler<IncomingRawMessage> for MyActor {
 /// #     type Result = ();
 /// #     fn handle(&mut self, msg: IncomingRawMessage, ctx: &mut Context<Self>) -> Self::Result {
-/// #         println!("Received: {}", msg.0);
+/// #         println!("Received on {}: {}", msg.connection_id, msg.raw);
 /// #     }
 /// # }
 /// # struct MyActor;
 /// # impl Actor for MyActor { type Context = Context<Self>; }
 /// #
-/// # async fn setup() -> anyhow::Result<()> {
+/// # async fn setup() -> Result<(), Box<dyn std::error::Error>> {
 /// let system = System::new();
 /// let my_actor_addr = MyActor.start(); // Actor to handle incoming messages
 /// let msg_handler: Recipient<IncomingRawMessage> = my_actor_addr.recipient();
@@ -67,10 +92,18 @@ pub use websocket::WebSocketTransport;
 ///     connect_timeout: Duration::from_secs(10),
 ///     request_timeout: Duration::from_secs(30),
 ///     ws_config: None, // Use default tungstenite config
+///     reconnect: Some(ReconnectPolicy::default()), // Or None to stop on disconnect
+///     keepalive: Some(Keepalive { ping_interval: Duration::from_secs(15), pong_timeout: Duration::from_secs(10) }),
+///     outbound_queue: OutboundQueue { capacity: 1024, overflow: OverflowPolicy::Wait },
+///     proxy: None, // Or Some(ProxyConfig { url: "socks5://bastion:1080".to_string(), credentials: None })
+///     tls: Default::default(), // Extra CA bundle, client certificate, ... for wss://
+///     headers: [("Authorization".to_string(), "Bearer <token>".to_string())].into(),
+///     query_params: Default::default(),
+///     recorder: None, // Or TrafficRecorder::from_config(&config.transport.recording)?
 /// };
 ///
-/// let connection_actor_addr = create_transport_actor(connection_id, params, msg_handler, None)?;
-/// // Now you can send SendRawMessage to connection_actor_addr
+/// let connection = create_transport_actor(connection_id, params, msg_handler, None)?;
+/// // Now you can send SendRawMessage to connection.sender
 /// # Ok(())
 /// # }
 /// ```
@@ -79,14 +112,14 @@ pub fn create_transport_actor(
     params: ConnectParams,
     message_handler: Recipient<IncomingRawMessage>,
     supervisor: Option<Recipient<ConnectionStatusUpdate>>,
-) -> Result<Addr<ConnectionActor<WebSocketTransport>>, TransportError> { // Return concrete Addr type
+) -> Result<ConnectionHandle, TransportError> {
     let url_scheme = url::Url::parse(&params.url)
         .map_err(|e| TransportError::InvalidUrl(e.to_string()))?
         .scheme()
         .to_lowercase();
 
     match url_scheme.as_str() {
-        "ws" | "wss" => {
+        "ws" | "wss" | "http" => {
             // Start the WebSocket specific connection actor
             let actor = ConnectionActor::<WebSocketTransport>::new(
                 id, // Pass connection ID
@@ -94,20 +127,34 @@ pub fn create_transport_actor(
                 message_handler,
                 supervisor,
             );
-            let addr = actor.start();
-            Ok(addr) // Return concrete Addr
+            Ok(ConnectionHandle::start(actor))
+        }
+        "pipe" => {
+            #[cfg(unix)]
+            {
+                let actor = ConnectionActor::<PipeTransport>::new(id, params, message_handler, supervisor);
+                Ok(ConnectionHandle::start(actor))
+            }
+            #[cfg(not(unix))]
+            {
+                Err(TransportError::UnsupportedScheme("pipe (only supported on unix)".to_string()))
+            }
+        }
+        "replay" => {
+            let actor = ConnectionActor::<ReplayTransport>::new(id, params, message_handler, supervisor);
+            Ok(ConnectionHandle::start(actor))
+        }
+        "tcp" => {
+            #[cfg(feature = "tcp")]
+            {
+                let actor = ConnectionActor::<TcpTransport>::new(id, params, message_handler, supervisor);
+                Ok(ConnectionHandle::start(actor))
+            }
+            #[cfg(not(feature = "tcp"))]
+            {
+                Err(TransportError::UnsupportedScheme("tcp (feature not enabled)".to_string()))
+            }
         }
-        // Example for future TCP transport:
-        // "tcp" => {
-        //     #[cfg(feature = "tcp")]
-        //     {
-        //         Ok(ConnectionActor::<TcpTransport>::new(params, message_handler, supervisor).start())
-        //     }
-        //     #[cfg(not(feature = "tcp"))]
-        //     {
-        //         Err(TransportError::UnsupportedScheme("tcp (feature not enabled)".to_string()))
-        //     }
-        // }
         _ => Err(TransportError::UnsupportedScheme(url_scheme)),
     }
 }
@@ -0,0 +1,240 @@
+//! Per-connection traffic counters and command round-trip latency histograms.
+//!
+//! `ConnectionActor` updates a `ConnectionMetrics` as frames cross the transport, and
+//! its `ConnectionHandle` shares the same counters, so a snapshot can be taken at any
+//! time without messaging the actor. Round trips are timed from the moment a command
+//! is written to the transport until its response arrives, so they exclude time spent
+//! in the outbound queue, which is tracked separately as `queue_wait`.
+
+use crate::connection::ConnectionId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Inclusive upper bounds, in milliseconds, of the latency histogram buckets.
+/// One more overflow bucket counts everything slower.
+pub const LATENCY_BUCKETS_MS: [u64; 13] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Commands still awaiting a response beyond this many are not timed, so a peer that
+/// never answers cannot grow the table without bound.
+const MAX_TIMED_COMMANDS: usize = 4096;
+
+/// Fixed-bucket latency histogram.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Count per bucket of `LATENCY_BUCKETS_MS`, followed by the overflow bucket.
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS_MS.len() + 1];
+        }
+        let ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|&le| ms <= le).unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+
+        let us = latency.as_micros() as u64;
+        self.count += 1;
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_micros(self.total_us / self.count))
+    }
+
+    /// Upper bound of the bucket containing quantile `q` (`0.0..=1.0`). Falls back to
+    /// the maximum seen when the quantile lands in the overflow bucket.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(match LATENCY_BUCKETS_MS.get(bucket) {
+                    Some(&le) => Duration::from_millis(le).min(Duration::from_micros(self.max_us)),
+                    None => Duration::from_micros(self.max_us),
+                });
+            }
+        }
+        Some(Duration::from_micros(self.max_us))
+    }
+}
+
+/// Point-in-time copy of a connection's metrics.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionMetricsSnapshot {
+    pub connection_id: ConnectionId,
+    pub frames_in: u64,
+    pub frames_out: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Sends that failed: rejected by the outbound queue, attempted while not connected,
+    /// or failed by the transport sink.
+    pub send_errors: u64,
+    /// Successful reconnects after the initial connection.
+    pub reconnects: u64,
+    /// Time messages spent in the outbound queue before reaching the transport.
+    pub queue_wait: LatencyHistogram,
+    /// Command round trips on the wire, keyed by CDP method.
+    pub commands: BTreeMap<String, LatencyHistogram>,
+}
+
+/// Live metrics for one connection. Clones share the same counters.
+#[derive(Debug, Clone)]
+pub struct ConnectionMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+#[derive(Debug)]
+struct MetricsInner {
+    connection_id: ConnectionId,
+    frames_in: AtomicU64,
+    frames_out: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    send_errors: AtomicU64,
+    reconnects: AtomicU64,
+    latency: Mutex<Latency>,
+}
+
+#[derive(Debug, Default)]
+struct Latency {
+    queue_wait: LatencyHistogram,
+    commands: HashMap<String, LatencyHistogram>,
+    /// Commands written but not yet answered, keyed by session and id.
+    in_flight: HashMap<(Option<String>, u64), (String, Instant)>,
+}
+
+/// The fields needed to pair commands with responses; everything else is skipped.
+#[derive(Deserialize)]
+struct Envelope {
+    id: Option<u64>,
+    method: Option<String>,
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+}
+
+impl ConnectionMetrics {
+    pub fn new(connection_id: ConnectionId) -> Self {
+        Self {
+            inner: Arc::new(MetricsInner {
+                connection_id,
+                frames_in: AtomicU64::new(0),
+                frames_out: AtomicU64::new(0),
+                bytes_in: AtomicU64::new(0),
+                bytes_out: AtomicU64::new(0),
+                send_errors: AtomicU64::new(0),
+                reconnects: AtomicU64::new(0),
+                latency: Mutex::new(Latency::default()),
+            }),
+        }
+    }
+
+    /// A frame was handed to the transport after waiting in the queue since `queued_at`.
+    pub(crate) fn frame_out(&self, frame: &str, queued_at: Instant) {
+        let now = Instant::now();
+        self.inner.frames_out.fetch_add(1, Ordering::Relaxed);
+        self.inner.bytes_out.fetch_add(frame.len() as u64, Ordering::Relaxed);
+
+        let mut latency = self.inner.latency.lock().unwrap();
+        latency.queue_wait.record(now.saturating_duration_since(queued_at));
+        if latency.in_flight.len() >= MAX_TIMED_COMMANDS {
+            return;
+        }
+        if let Ok(Envelope { id: Some(id), method: Some(method), session_id }) = serde_json::from_str(frame) {
+            latency.in_flight.insert((session_id, id), (method, now));
+        }
+    }
+
+    pub(crate) fn frame_in(&self, frame: &str) {
+        self.inner.frames_in.fetch_add(1, Ordering::Relaxed);
+        self.inner.bytes_in.fetch_add(frame.len() as u64, Ordering::Relaxed);
+
+        let mut latency = self.inner.latency.lock().unwrap();
+        // Events make up most inbound traffic; skip parsing when nothing is awaited
+        if latency.in_flight.is_empty() {
+            return;
+        }
+        if let Ok(Envelope { id: Some(id), method: None, session_id }) = serde_json::from_str(frame) {
+            if let Some((method, sent_at)) = latency.in_flight.remove(&(session_id, id)) {
+                latency.commands.entry(method).or_default().record(sent_at.elapsed());
+            }
+        }
+    }
+
+    pub(crate) fn send_error(&self) {
+        self.inner.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn reconnected(&self) {
+        self.inner.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Forgets commands in flight; their responses will never arrive on a new socket.
+    pub(crate) fn connection_lost(&self) {
+        self.inner.latency.lock().unwrap().in_flight.clear();
+    }
+
+    pub fn snapshot(&self) -> ConnectionMetricsSnapshot {
+        let inner = &self.inner;
+        let latency = inner.latency.lock().unwrap();
+        ConnectionMetricsSnapshot {
+            connection_id: inner.connection_id,
+            frames_in: inner.frames_in.load(Ordering::Relaxed),
+            frames_out: inner.frames_out.load(Ordering::Relaxed),
+            bytes_in: inner.bytes_in.load(Ordering::Relaxed),
+            bytes_out: inner.bytes_out.load(Ordering::Relaxed),
+            send_errors: inner.send_errors.load(Ordering::Relaxed),
+            reconnects: inner.reconnects.load(Ordering::Relaxed),
+            queue_wait: latency.queue_wait.clone(),
+            commands: latency.commands.iter().map(|(method, histogram)| (method.clone(), histogram.clone())).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_pair_commands_with_responses() {
+        let metrics = ConnectionMetrics::new(3);
+        let queued = Instant::now();
+        metrics.frame_out(r#"{"id":1,"method":"Page.navigate","params":{"url":"about:blank"}}"#, queued);
+        metrics.frame_out(r#"{"id":1,"method":"Runtime.evaluate","sessionId":"S1"}"#, queued);
+        metrics.frame_in(r#"{"method":"Page.loadEventFired","params":{}}"#);
+        metrics.frame_in(r#"{"id":1,"result":{},"sessionId":"S1"}"#);
+        metrics.frame_in(r#"{"id":1,"result":{"frameId":"F"}}"#);
+        metrics.frame_in(r#"{"id":1,"result":{}}"#); // Duplicate, nothing in flight
+        metrics.send_error();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.frames_out, snapshot.frames_in, snapshot.send_errors), (2, 4, 1));
+        assert_eq!(snapshot.bytes_in, 134);
+        assert_eq!(snapshot.commands["Page.navigate"].count, 1);
+        assert_eq!(snapshot.commands["Runtime.evaluate"].count, 1);
+        assert_eq!(snapshot.queue_wait.count, 2);
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["connection_id"], 3);
+
+        let mut histogram = LatencyHistogram::default();
+        for ms in [1, 3, 3, 40, 20_000] {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(5)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_secs(20)));
+        assert_eq!(histogram.buckets.iter().sum::<u64>(), 5);
+    }
+}
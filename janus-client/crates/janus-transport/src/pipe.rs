@@ -0,0 +1,191 @@
+use crate::connection::{ConnectParams, ConnectionCodec, Transport};
+use janus_common::error::TransportError;
+use async_trait::async_trait;
+use futures_util::sink::Sink;
+use futures_util::stream::Stream;
+use std::os::fd::AsRawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::unix::pipe;
+use tokio::process::{Child, Command};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use url::Url;
+
+/// How long `disconnect` waits for the browser to exit after its pipe closes.
+const EXIT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Transport for Chrome's `--remote-debugging-pipe` mode.
+///
+/// The browser is spawned as a child process. It reads commands from its fd 3
+/// and writes responses and events to its fd 4, each message terminated by a NUL byte.
+///
+/// URL format: `pipe:///path/to/chrome?arg=--headless=new&arg=--no-sandbox`.
+/// `--remote-debugging-pipe` is appended to the arguments automatically.
+#[derive(Debug)]
+pub struct PipeTransport {
+    reader: FramedRead<pipe::Receiver, ConnectionCodec>,
+}
+
+/// Write half of a `PipeTransport`. Owns the browser process, which is killed
+/// if the sink is dropped without `disconnect`.
+#[derive(Debug)]
+pub struct PipeSink {
+    writer: FramedWrite<pipe::Sender, ConnectionCodec>,
+    child: Child,
+}
+
+/// Parses a `pipe:` URL into the executable path and its extra arguments.
+fn parse_pipe_url(raw: &str) -> Result<(String, Vec<String>), TransportError> {
+    let url = Url::parse(raw).map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+    if url.scheme() != "pipe" {
+        return Err(TransportError::UnsupportedScheme(url.scheme().to_string()));
+    }
+    if url.path().is_empty() {
+        return Err(TransportError::InvalidUrl(format!("No executable path in '{}'", raw)));
+    }
+
+    let args = url.query_pairs()
+        .filter(|(key, _)| key == "arg")
+        .map(|(_, value)| value.into_owned())
+        .collect();
+    Ok((url.path().to_string(), args))
+}
+
+#[async_trait]
+impl Transport for PipeTransport {
+    type Sink = PipeSink;
+
+    async fn connect(params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
+        let (program, args) = parse_pipe_url(&params.url)?;
+        log::debug!("Launching {} in pipe mode with args {:?}", program, args);
+
+        // Commands flow parent -> child fd 3, responses child fd 4 -> parent
+        let (command_tx, command_rx) = pipe::pipe()?;
+        let (response_tx, response_rx) = pipe::pipe()?;
+        // The child's ends must be blocking; tokio creates them non-blocking
+        let child_read = command_rx.into_blocking_fd()?;
+        let child_write = response_tx.into_blocking_fd()?;
+        let (child_read_fd, child_write_fd) = (child_read.as_raw_fd(), child_write.as_raw_fd());
+
+        let mut command = Command::new(&program);
+        command.args(&args)
+            .arg("--remote-debugging-pipe")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+
+        // SAFETY: only async-signal-safe libc calls run between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                // Move both ends above fd 4 first so installing one cannot clobber the other
+                let read_fd = libc::fcntl(child_read_fd, libc::F_DUPFD, 5);
+                let write_fd = libc::fcntl(child_write_fd, libc::F_DUPFD, 5);
+                if read_fd < 0 || write_fd < 0
+                    || libc::dup2(read_fd, 3) < 0
+                    || libc::dup2(write_fd, 4) < 0
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                libc::close(read_fd);
+                libc::close(write_fd);
+                Ok(())
+            });
+        }
+
+        let child = command.spawn().map_err(|e| {
+            TransportError::ConnectionFailed(format!("Failed to launch '{}': {}", program, e))
+        })?;
+        // The child holds its own copies now
+        drop(child_read);
+        drop(child_write);
+
+        log::info!("Browser launched in pipe mode (pid {:?})", child.id());
+
+        // Messages are capped like WebSocket messages
+        #[cfg(feature = "websocket")]
+        let max_message_size = params.ws_config.as_ref().and_then(|config| config.max_message_size);
+        #[cfg(not(feature = "websocket"))]
+        let max_message_size = None;
+
+        let codec = ConnectionCodec::nul_delimited().with_max_message_size(max_message_size);
+        Ok((
+            PipeTransport { reader: FramedRead::new(response_rx, codec) },
+            PipeSink { writer: FramedWrite::new(command_tx, ConnectionCodec::nul_delimited()), child },
+        ))
+    }
+
+    async fn disconnect(sink: Self::Sink) -> Result<(), TransportError> {
+        let PipeSink { writer, mut child } = sink;
+        // Closing the command pipe asks the browser to shut down
+        drop(writer);
+
+        match tokio::time::timeout(EXIT_GRACE_PERIOD, child.wait()).await {
+            Ok(Ok(status)) => {
+                log::debug!("Pipe-mode browser exited with {}", status);
+                Ok(())
+            }
+            Ok(Err(e)) => Err(TransportError::Io(e.to_string())),
+            Err(_) => {
+                log::warn!("Pipe-mode browser did not exit within {:?}, killing it", EXIT_GRACE_PERIOD);
+                child.kill().await.map_err(TransportError::from)
+            }
+        }
+    }
+}
+
+impl Stream for PipeTransport {
+    type Item = Result<String, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.reader).poll_next(cx)
+    }
+}
+
+impl Sink<String> for PipeSink {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: String) -> Result<(), Self::Error> {
+        Pin::new(&mut self.writer).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+
+    fn params(url: &str) -> ConnectParams {
+        ConnectParams {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            ..ConnectParams::new(url)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipe_round_trip_with_echo_child() {
+        // `sh -c 'cat <&3 >&4'` echoes every frame back, standing in for the browser
+        let url = "pipe:///bin/sh?arg=-c&arg=cat%20%3C%263%20%3E%264";
+        let (mut reader, mut writer) = PipeTransport::connect(params(url)).await.unwrap();
+
+        writer.send(r#"{"id":1,"method":"Browser.getVersion"}"#.to_string()).await.unwrap();
+        writer.send(r#"{"id":2,"method":"Target.getTargets"}"#.to_string()).await.unwrap();
+
+        assert_eq!(reader.next().await.unwrap().unwrap(), r#"{"id":1,"method":"Browser.getVersion"}"#);
+        assert_eq!(reader.next().await.unwrap().unwrap(), r#"{"id":2,"method":"Target.getTargets"}"#);
+
+        PipeTransport::disconnect(writer).await.unwrap();
+    }
+}
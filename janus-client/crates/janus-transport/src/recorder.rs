@@ -0,0 +1,220 @@
+//! Wire-level traffic recording: every frame a connection sends or receives, as JSONL.
+//!
+//! Each line looks like
+//! `{"elapsed_us":1234,"connection":1,"direction":"out","len":52,"frame":{...}}`.
+//! `elapsed_us` is monotonic from when the recorder was opened, so several
+//! connections sharing one recorder can be interleaved correctly.
+
+use crate::connection::ConnectionId;
+use janus_common::config::RecordingConfig;
+use janus_common::error::TransportError;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Placeholder written in place of redacted field values.
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Inbound => "in",
+            Direction::Outbound => "out",
+        }
+    }
+}
+
+/// Shared handle to a JSONL recording file. Cloning shares the file.
+///
+/// Lines are written by a background thread, so recording never blocks an actor on disk I/O.
+#[derive(Clone)]
+pub struct TrafficRecorder {
+    inner: Arc<RecorderInner>,
+}
+
+struct RecorderInner {
+    config: RecordingConfig,
+    started: Instant,
+    bytes_written: AtomicU64,
+    capped: AtomicBool,
+    lines: Option<mpsc::Sender<String>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for TrafficRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrafficRecorder").field("path", &self.inner.config.path).finish()
+    }
+}
+
+impl TrafficRecorder {
+    /// Opens (truncating) the file at `config.path`. Returns `None` when no path is configured.
+    pub fn from_config(config: &RecordingConfig) -> Result<Option<Self>, TransportError> {
+        let Some(path) = &config.path else { return Ok(None) };
+        let file = File::create(path)
+            .map_err(|e| TransportError::Io(format!("Cannot create traffic recording '{}': {}", path, e)))?;
+
+        let (tx, rx) = mpsc::channel::<String>();
+        let writer = std::thread::Builder::new()
+            .name("janus-recorder".to_string())
+            .spawn(move || write_lines(BufWriter::new(file), rx))
+            .map_err(|e| TransportError::Internal(format!("Cannot start recorder thread: {}", e)))?;
+
+        log::info!("Recording connection traffic to {}", path);
+        Ok(Some(Self {
+            inner: Arc::new(RecorderInner {
+                config: config.clone(),
+                started: Instant::now(),
+                bytes_written: AtomicU64::new(0),
+                capped: AtomicBool::new(false),
+                lines: Some(tx),
+                writer: Some(writer),
+            }),
+        }))
+    }
+
+    /// Records one frame. Frames over `max_frame_bytes` are cut short and flagged
+    /// `"truncated": true`; nothing is recorded once the file reaches `max_file_bytes`.
+    pub fn record(&self, connection: ConnectionId, direction: Direction, frame: &str) {
+        let inner = &self.inner;
+        if inner.capped.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut entry = serde_json::json!({
+            "elapsed_us": inner.started.elapsed().as_micros() as u64,
+            "connection": connection,
+            "direction": direction.as_str(),
+            "len": frame.len(),
+        });
+        let (recorded, truncated) = match serde_json::from_str::<Value>(frame) {
+            Ok(mut value) => {
+                redact(&mut value, &inner.config.redact_fields);
+                let text = value.to_string();
+                if text.len() <= inner.config.max_frame_bytes {
+                    (value, false)
+                } else {
+                    (Value::String(truncate(&text, inner.config.max_frame_bytes)), true)
+                }
+            }
+            // Not JSON (e.g. a bulk payload); recorded verbatim, nothing to redact
+            Err(_) => (
+                Value::String(truncate(frame, inner.config.max_frame_bytes)),
+                frame.len() > inner.config.max_frame_bytes,
+            ),
+        };
+        entry["frame"] = recorded;
+        if truncated {
+            entry["truncated"] = Value::Bool(true);
+        }
+
+        let line = entry.to_string() + "\n";
+        let total = inner.bytes_written.fetch_add(line.len() as u64, Ordering::Relaxed) + line.len() as u64;
+        if total > inner.config.max_file_bytes {
+            if !inner.capped.swap(true, Ordering::Relaxed) {
+                log::warn!("Traffic recording {:?} reached {} bytes, no longer recording", inner.config.path, inner.config.max_file_bytes);
+            }
+            return;
+        }
+        if let Some(lines) = &inner.lines {
+            let _ = lines.send(line);
+        }
+    }
+}
+
+impl Drop for RecorderInner {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer flush and exit
+        self.lines.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_lines(mut out: BufWriter<File>, lines: mpsc::Receiver<String>) {
+    while let Ok(line) = lines.recv() {
+        let mut result = out.write_all(line.as_bytes());
+        // Write whatever else is queued, then flush so a crash loses as little as possible
+        while let (Ok(()), Ok(line)) = (&result, lines.try_recv()) {
+            result = out.write_all(line.as_bytes());
+        }
+        if let Err(e) = result.and_then(|_| out.flush()) {
+            log::error!("Traffic recording write failed, stopping recorder: {}", e);
+            return;
+        }
+    }
+}
+
+/// Replaces the value of every key named in `fields`, at any depth.
+fn redact(value: &mut Value, fields: &[String]) {
+    if fields.is_empty() {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if fields.iter().any(|field| field == key) {
+                    *child = Value::String(REDACTED.to_string());
+                } else {
+                    redact(child, fields);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, fields)),
+        _ => {}
+    }
+}
+
+fn truncate(text: &str, max_bytes: usize) -> String {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_redacts_truncates_and_caps() {
+        let path = std::env::temp_dir().join(format!("janus-recorder-{}.jsonl", std::process::id()));
+        let config = RecordingConfig {
+            path: Some(path.to_string_lossy().to_string()),
+            max_frame_bytes: 80,
+            max_file_bytes: 600,
+            redact_fields: vec!["password".to_string()],
+        };
+        let recorder = TrafficRecorder::from_config(&config).unwrap().unwrap();
+        recorder.record(7, Direction::Outbound, r#"{"id":1,"method":"Auth.login","params":{"password":"hunter2"}}"#);
+        recorder.record(7, Direction::Inbound, &format!(r#"{{"id":1,"result":{{"data":"{}"}}}}"#, "x".repeat(100)));
+        for _ in 0..10 {
+            recorder.record(7, Direction::Inbound, r#"{"method":"Page.loadEventFired","params":{}}"#);
+        }
+        drop(recorder); // Joins the writer thread
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<Value> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(lines[0]["direction"], "out");
+        assert_eq!(lines[0]["connection"], 7);
+        assert_eq!(lines[0]["frame"]["params"]["password"], REDACTED);
+        assert_eq!(lines[1]["truncated"], true);
+        assert_eq!(lines[1]["frame"].as_str().unwrap().len(), 80);
+        assert!(lines[0]["elapsed_us"].as_u64() <= lines[1]["elapsed_us"].as_u64());
+        assert!(contents.len() <= 600 && lines.len() < 12, "file cap not applied: {} lines", lines.len());
+    }
+}
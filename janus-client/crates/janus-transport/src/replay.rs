@@ -0,0 +1,422 @@
+//! Transport that plays back a traffic recording (see `recorder`) instead of talking to a browser.
+//!
+//! URL format: `replay:///path/to/session.jsonl?timing=instant&connection=3`.
+//! `timing` is `original` (default, inbound frames keep their recorded delays) or `instant`.
+//! `connection` picks one connection out of a shared recording; by default the first one
+//! in the file is used.
+//!
+//! Every command sent is matched against the unused recorded commands by method, `sessionId`
+//! and params; ids are ignored. Its recorded response is sent back with the live id, along
+//! with the events that arrived before the next recorded command. Redacted param values
+//! match anything. A command with no recorded match gets a CDP error response.
+
+use crate::connection::{ConnectParams, ConnectionId, Transport};
+use janus_common::error::TransportError;
+use async_trait::async_trait;
+use futures_util::sink::Sink;
+use futures_util::stream::Stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Value the recorder writes in place of redacted fields.
+const REDACTED: &str = "<redacted>";
+
+/// How recorded inbound frames are paced during replay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplayTiming {
+    /// Keep each frame's delay relative to the command that preceded it in the recording.
+    #[default]
+    Original,
+    /// Deliver frames as soon as the command that triggers them is sent.
+    Instant,
+}
+
+/// Read half of a replayed session.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    incoming: mpsc::UnboundedReceiver<String>,
+}
+
+/// Write half of a replayed session. Matches commands against the recording.
+#[derive(Debug)]
+pub struct ReplaySink {
+    script: Script,
+    timing: ReplayTiming,
+    frames: Option<mpsc::UnboundedSender<String>>,
+    pacers: Vec<JoinHandle<()>>,
+}
+
+/// One line of a recording, as written by `TrafficRecorder`.
+#[derive(Debug, Deserialize)]
+struct RecordedFrame {
+    elapsed_us: u64,
+    connection: ConnectionId,
+    direction: String,
+    frame: Value,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledFrame {
+    delay: Duration,
+    frame: Value,
+    /// The response to the owning command, whose id is rewritten on replay.
+    is_response: bool,
+}
+
+#[derive(Debug)]
+struct RecordedCommand {
+    method: String,
+    session_id: Option<String>,
+    params: Value,
+    sent_at_us: u64,
+    replies: Vec<ScheduledFrame>,
+    used: bool,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    /// Frames received before the first command, sent right after connecting.
+    opening: Vec<ScheduledFrame>,
+    commands: Vec<RecordedCommand>,
+}
+
+impl Script {
+    fn load(path: &str, connection: Option<ConnectionId>) -> Result<Self, TransportError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| TransportError::ConnectionFailed(format!("Cannot read recording '{}': {}", path, e)))?;
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let entry: RecordedFrame = serde_json::from_str(line)
+                .map_err(|e| TransportError::Serde(format!("{}:{}: {}", path, index + 1, e)))?;
+            entries.push(entry);
+        }
+
+        let connection = connection.or_else(|| entries.first().map(|entry| entry.connection));
+        entries.retain(|entry| Some(entry.connection) == connection);
+        Ok(Self::from_entries(entries))
+    }
+
+    fn from_entries(entries: Vec<RecordedFrame>) -> Self {
+        let start_us = entries.first().map_or(0, |entry| entry.elapsed_us);
+        let mut script = Script::default();
+        let mut by_recorded_id: HashMap<i64, usize> = HashMap::new();
+
+        for entry in entries {
+            if entry.truncated {
+                log::warn!("Skipping truncated {} frame at {}us, it cannot be replayed", entry.direction, entry.elapsed_us);
+                continue;
+            }
+            match entry.direction.as_str() {
+                "out" => {
+                    let Some(method) = entry.frame.get("method").and_then(Value::as_str) else { continue };
+                    if let Some(id) = entry.frame.get("id").and_then(Value::as_i64) {
+                        by_recorded_id.insert(id, script.commands.len());
+                    }
+                    script.commands.push(RecordedCommand {
+                        method: method.to_string(),
+                        session_id: session_of(&entry.frame),
+                        params: params_of(&entry.frame),
+                        sent_at_us: entry.elapsed_us,
+                        replies: Vec::new(),
+                        used: false,
+                    });
+                }
+                "in" => {
+                    // Responses belong to their command; events to the latest command sent
+                    let response_to = entry.frame.get("id")
+                        .and_then(Value::as_i64)
+                        .and_then(|id| by_recorded_id.get(&id).copied());
+                    let owner = response_to.or_else(|| script.commands.len().checked_sub(1));
+                    match owner {
+                        Some(index) => {
+                            let command = &mut script.commands[index];
+                            command.replies.push(ScheduledFrame {
+                                delay: Duration::from_micros(entry.elapsed_us.saturating_sub(command.sent_at_us)),
+                                frame: entry.frame,
+                                is_response: response_to.is_some(),
+                            });
+                        }
+                        None => script.opening.push(ScheduledFrame {
+                            delay: Duration::from_micros(entry.elapsed_us.saturating_sub(start_us)),
+                            frame: entry.frame,
+                            is_response: false,
+                        }),
+                    }
+                }
+                other => log::warn!("Skipping frame with unknown direction '{}'", other),
+            }
+        }
+        script
+    }
+
+    /// Claims the first unused recorded command matching `command` and returns its replies.
+    fn take_match(&mut self, command: &Value) -> Option<Vec<ScheduledFrame>> {
+        let method = command.get("method").and_then(Value::as_str)?;
+        let session_id = session_of(command);
+        let params = params_of(command);
+
+        let recorded = self.commands.iter_mut().find(|recorded| {
+            !recorded.used
+                && recorded.method == method
+                && recorded.session_id == session_id
+                && params_match(&recorded.params, &params)
+        })?;
+        recorded.used = true;
+        Some(std::mem::take(&mut recorded.replies))
+    }
+}
+
+fn session_of(frame: &Value) -> Option<String> {
+    frame.get("sessionId").and_then(Value::as_str).map(str::to_string)
+}
+
+/// Missing params and `{}` are the same command.
+fn params_of(frame: &Value) -> Value {
+    frame.get("params").cloned().unwrap_or_else(|| json!({}))
+}
+
+fn params_match(recorded: &Value, live: &Value) -> bool {
+    match (recorded, live) {
+        (Value::String(redacted), _) if redacted == REDACTED => true,
+        (Value::Object(recorded), Value::Object(live)) => {
+            recorded.len() == live.len()
+                && recorded.iter().all(|(key, value)| live.get(key).is_some_and(|other| params_match(value, other)))
+        }
+        (Value::Array(recorded), Value::Array(live)) => {
+            recorded.len() == live.len() && recorded.iter().zip(live).all(|(a, b)| params_match(a, b))
+        }
+        _ => recorded == live,
+    }
+}
+
+/// Non-JSON frames were recorded as plain strings and go back out verbatim.
+fn render(frame: Value) -> String {
+    match frame {
+        Value::String(raw) => raw,
+        other => other.to_string(),
+    }
+}
+
+/// Parses a `replay:` URL into the recording path, timing and connection filter.
+fn parse_replay_url(raw: &str) -> Result<(String, ReplayTiming, Option<ConnectionId>), TransportError> {
+    let url = Url::parse(raw).map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+    if url.scheme() != "replay" {
+        return Err(TransportError::UnsupportedScheme(url.scheme().to_string()));
+    }
+    if url.path().is_empty() {
+        return Err(TransportError::InvalidUrl(format!("No recording path in '{}'", raw)));
+    }
+
+    let mut timing = ReplayTiming::default();
+    let mut connection = None;
+    for (key, value) in url.query_pairs() {
+        match (key.as_ref(), value.as_ref()) {
+            ("timing", "original") => timing = ReplayTiming::Original,
+            ("timing", "instant") => timing = ReplayTiming::Instant,
+            ("connection", id) => {
+                connection = Some(id.parse().map_err(|_| {
+                    TransportError::InvalidUrl(format!("Invalid connection id '{}' in '{}'", id, raw))
+                })?)
+            }
+            (key, value) => {
+                return Err(TransportError::InvalidUrl(format!("Unknown replay option {}={} in '{}'", key, value, raw)))
+            }
+        }
+    }
+    Ok((url.path().to_string(), timing, connection))
+}
+
+impl ReplaySink {
+    fn deliver(&mut self, replies: Vec<ScheduledFrame>, live_id: &Value) {
+        let Some(frames) = &self.frames else { return };
+        let replies: Vec<(Duration, String)> = replies.into_iter()
+            .map(|reply| {
+                let mut frame = reply.frame;
+                if reply.is_response {
+                    frame["id"] = live_id.clone();
+                }
+                (reply.delay, render(frame))
+            })
+            .collect();
+
+        match self.timing {
+            ReplayTiming::Instant => {
+                for (_, frame) in replies {
+                    let _ = frames.send(frame);
+                }
+            }
+            ReplayTiming::Original => {
+                let frames = frames.clone();
+                let started = tokio::time::Instant::now();
+                self.pacers.retain(|pacer| !pacer.is_finished());
+                self.pacers.push(tokio::spawn(async move {
+                    for (delay, frame) in replies {
+                        tokio::time::sleep_until(started + delay).await;
+                        if frames.send(frame).is_err() {
+                            return;
+                        }
+                    }
+                }));
+            }
+        }
+    }
+}
+
+impl Drop for ReplaySink {
+    fn drop(&mut self) {
+        // Frames still waiting for their recorded delay would otherwise keep the stream open
+        for pacer in &self.pacers {
+            pacer.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    type Sink = ReplaySink;
+
+    async fn connect(params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
+        let (path, timing, connection) = parse_replay_url(&params.url)?;
+        let mut script = Script::load(&path, connection)?;
+        log::info!("Replaying {} recorded commands from {} ({:?} timing)", script.commands.len(), path, timing);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let opening = std::mem::take(&mut script.opening);
+        let mut sink = ReplaySink { script, timing, frames: Some(tx), pacers: Vec::new() };
+        sink.deliver(opening, &Value::Null);
+        Ok((ReplayTransport { incoming: rx }, sink))
+    }
+
+    async fn disconnect(sink: Self::Sink) -> Result<(), TransportError> {
+        let unused = sink.script.commands.iter().filter(|command| !command.used).count();
+        if unused > 0 {
+            log::debug!("Replay ended with {} recorded commands never sent", unused);
+        }
+        drop(sink);
+        Ok(())
+    }
+}
+
+impl Stream for ReplayTransport {
+    type Item = Result<String, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx).map(|frame| frame.map(Ok))
+    }
+}
+
+impl Sink<String> for ReplaySink {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(if self.frames.is_some() { Ok(()) } else { Err(TransportError::NotConnected) })
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: String) -> Result<(), Self::Error> {
+        if self.frames.is_none() {
+            return Err(TransportError::NotConnected);
+        }
+        let command: Value = serde_json::from_str(&item).map_err(|e| TransportError::Serde(e.to_string()))?;
+        let live_id = command.get("id").cloned().unwrap_or(Value::Null);
+
+        let replies = match self.script.take_match(&command) {
+            Some(replies) => replies,
+            None => {
+                let method = command.get("method").and_then(Value::as_str).unwrap_or("<no method>");
+                log::warn!("No recorded match for {} {}", method, params_of(&command));
+                vec![ScheduledFrame {
+                    delay: Duration::ZERO,
+                    frame: json!({
+                        "id": live_id,
+                        "error": { "code": -32000, "message": format!("No recorded response for {}", method) },
+                    }),
+                    is_response: false,
+                }]
+            }
+        };
+        self.deliver(replies, &live_id);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.frames = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+
+    const RECORDING: &str = r#"
+{"elapsed_us":0,"connection":4,"direction":"in","len":0,"frame":{"method":"Target.targetCreated","params":{"targetInfo":{"targetId":"T1"}}}}
+{"elapsed_us":10,"connection":9,"direction":"out","len":0,"frame":{"id":1,"method":"Browser.getVersion","params":{}}}
+{"elapsed_us":100,"connection":4,"direction":"out","len":0,"frame":{"id":7,"method":"Target.attachToTarget","params":{"targetId":"T1","flatten":true}}}
+{"elapsed_us":150,"connection":4,"direction":"out","len":0,"frame":{"id":8,"method":"Auth.login","params":{"password":"<redacted>"}}}
+{"elapsed_us":40150,"connection":4,"direction":"in","len":0,"frame":{"id":8,"result":{}}}
+{"elapsed_us":60000,"connection":4,"direction":"in","len":0,"frame":{"id":7,"result":{"sessionId":"S1"}}}
+{"elapsed_us":60100,"connection":4,"direction":"in","len":0,"frame":{"method":"Page.loadEventFired","params":{}}}
+"#;
+
+    fn params(url: String) -> ConnectParams {
+        ConnectParams {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            ..ConnectParams::new(url)
+        }
+    }
+
+    async fn next_json(reader: &mut ReplayTransport) -> Value {
+        serde_json::from_str(&reader.next().await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_commands_and_rewrites_ids() {
+        let path = std::env::temp_dir().join(format!("janus-replay-{}.jsonl", std::process::id()));
+        std::fs::write(&path, RECORDING).unwrap();
+
+        let url = format!("replay://{}?timing=instant", path.display());
+        let (mut reader, mut writer) = ReplayTransport::connect(params(url)).await.unwrap();
+        assert_eq!(next_json(&mut reader).await["method"], "Target.targetCreated");
+
+        // Other connection's commands are not part of the script
+        writer.send(r#"{"id":1,"method":"Browser.getVersion"}"#.to_string()).await.unwrap();
+        assert!(next_json(&mut reader).await["error"]["message"].as_str().unwrap().contains("Browser.getVersion"));
+
+        writer.send(r#"{"id":101,"method":"Target.attachToTarget","params":{"flatten":true,"targetId":"T1"}}"#.to_string()).await.unwrap();
+        let attached = next_json(&mut reader).await;
+        assert_eq!(attached["id"], 101);
+        assert_eq!(attached["result"]["sessionId"], "S1");
+
+        writer.send(r#"{"id":102,"method":"Auth.login","params":{"password":"hunter2"}}"#.to_string()).await.unwrap();
+        assert_eq!(next_json(&mut reader).await, json!({"id":102,"result":{}}));
+        assert_eq!(next_json(&mut reader).await["method"], "Page.loadEventFired");
+        ReplayTransport::disconnect(writer).await.unwrap();
+
+        // Original timing keeps the recorded 40ms between the command and its response
+        let url = format!("replay://{}?connection=4", path.display());
+        let (mut reader, mut writer) = ReplayTransport::connect(params(url)).await.unwrap();
+        next_json(&mut reader).await;
+        let sent = std::time::Instant::now();
+        writer.send(r#"{"id":1,"method":"Auth.login","params":{"password":"x"}}"#.to_string()).await.unwrap();
+        assert_eq!(next_json(&mut reader).await["id"], 1);
+        assert!(sent.elapsed() >= Duration::from_millis(40), "replied after {:?}", sent.elapsed());
+
+        let _ = std::fs::remove_file(&path);
+        assert!(parse_replay_url("replay:///tmp/x.jsonl?timing=slow").is_err());
+    }
+}
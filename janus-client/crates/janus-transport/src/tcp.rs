@@ -0,0 +1,109 @@
+use crate::connection::{ConnectParams, ConnectionCodec, Transport};
+use janus_common::error::TransportError;
+use async_trait::async_trait;
+use futures_util::sink::{Sink, SinkExt};
+use futures_util::stream::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::time::timeout;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use url::Url;
+
+/// Default port of Firefox's debugger server (`--start-debugger-server`).
+const DEFAULT_RDP_PORT: u16 = 6000;
+
+/// Raw TCP transport speaking Firefox Remote Debugging Protocol framing.
+///
+/// URL format: `tcp://host:port`. Packets are `<length>:<json>`; bulk packets are
+/// delivered as JSON envelopes (see `Framing::LengthPrefixed`).
+#[derive(Debug)]
+pub struct TcpTransport {
+    reader: FramedRead<OwnedReadHalf, ConnectionCodec>,
+}
+
+/// Write half of a `TcpTransport`.
+#[derive(Debug)]
+pub struct TcpSink {
+    writer: FramedWrite<OwnedWriteHalf, ConnectionCodec>,
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    type Sink = TcpSink;
+
+    async fn connect(params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
+        log::debug!("Connecting TCP to: {}", params.url);
+
+        let url = Url::parse(&params.url)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        let host = url.host_str()
+            .ok_or_else(|| TransportError::InvalidUrl(format!("No host in '{}'", params.url)))?
+            .to_string();
+        let port = url.port().unwrap_or(DEFAULT_RDP_PORT);
+
+        let stream = match timeout(params.connect_timeout, TcpStream::connect((host.as_str(), port))).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                log::error!("TCP connection error to {}: {}", params.url, e);
+                return Err(TransportError::ConnectionFailed(e.to_string()));
+            }
+            Err(_) => {
+                log::error!("TCP connection timed out after {:?} to {}", params.connect_timeout, params.url);
+                return Err(TransportError::Timeout(format!(
+                    "Connection timed out after {:?}",
+                    params.connect_timeout
+                )));
+            }
+        };
+        stream.set_nodelay(true)?;
+        log::info!("TCP connected successfully to {}", params.url);
+
+        // Packets are capped like WebSocket messages
+        #[cfg(feature = "websocket")]
+        let max_message_size = params.ws_config.as_ref().and_then(|config| config.max_message_size);
+        #[cfg(not(feature = "websocket"))]
+        let max_message_size = None;
+
+        let (read_half, write_half) = stream.into_split();
+        let codec = ConnectionCodec::length_prefixed().with_max_message_size(max_message_size);
+        Ok((
+            TcpTransport { reader: FramedRead::new(read_half, codec) },
+            TcpSink { writer: FramedWrite::new(write_half, ConnectionCodec::length_prefixed()) },
+        ))
+    }
+
+    async fn disconnect(mut sink: Self::Sink) -> Result<(), TransportError> {
+        // Flushes pending packets and shuts down the write side
+        sink.close().await
+    }
+}
+
+impl Stream for TcpTransport {
+    type Item = Result<String, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.reader).poll_next(cx)
+    }
+}
+
+impl Sink<String> for TcpSink {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: String) -> Result<(), Self::Error> {
+        Pin::new(&mut self.writer).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.writer).poll_close(cx)
+    }
+}
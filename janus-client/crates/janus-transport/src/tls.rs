@@ -0,0 +1,106 @@
+//! Builds native-tls connectors from `TlsConfig` (extra roots, client certificates).
+
+use janus_common::config::TlsConfig;
+use janus_common::error::TransportError;
+use native_tls::{Certificate, Identity, TlsConnector};
+
+/// Builds a connector for `config`, or `None` when the defaults apply and the
+/// library's own connector can be used.
+pub fn build_connector(config: &TlsConfig) -> Result<Option<TlsConnector>, TransportError> {
+    if *config == TlsConfig::default() {
+        return Ok(None);
+    }
+
+    let mut builder = TlsConnector::builder();
+
+    if let Some(path) = &config.ca_bundle_path {
+        let pem = read_pem(path, "CA bundle")?;
+        let certificates = split_pem_certificates(&pem);
+        if certificates.is_empty() {
+            return Err(TransportError::TlsError(format!("CA bundle '{}' contains no certificates", path)));
+        }
+        for (index, block) in certificates.iter().enumerate() {
+            let certificate = Certificate::from_pem(block.as_bytes()).map_err(|e| {
+                TransportError::TlsError(format!("Invalid certificate #{} in CA bundle '{}': {}", index + 1, path, e))
+            })?;
+            builder.add_root_certificate(certificate);
+        }
+        log::debug!("Trusting {} extra root certificate(s) from {}", certificates.len(), path);
+    }
+
+    match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = read_pem(cert_path, "client certificate")?;
+            let key = read_pem(key_path, "client key")?;
+            let identity = Identity::from_pkcs8(cert.as_bytes(), key.as_bytes()).map_err(|e| {
+                TransportError::TlsError(format!(
+                    "Invalid client identity ('{}' / '{}', key must be PKCS#8 PEM): {}",
+                    cert_path, key_path, e
+                ))
+            })?;
+            builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(TransportError::TlsError(
+                "client_cert_path and client_key_path must be set together".to_string(),
+            ))
+        }
+    }
+
+    if config.danger_accept_invalid_certs {
+        log::warn!("TLS certificate validation is disabled (danger_accept_invalid_certs)");
+        builder.danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| TransportError::TlsError(format!("Failed to build TLS connector: {}", e)))
+}
+
+fn read_pem(path: &str, what: &str) -> Result<String, TransportError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| TransportError::TlsError(format!("Cannot read {} '{}': {}", what, path, e)))
+}
+
+/// `Certificate::from_pem` only takes the first certificate, so bundles are split first.
+fn split_pem_certificates(pem: &str) -> Vec<String> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(len) = rest[start..].find(END) else { break };
+        let end = start + len + END.len();
+        blocks.push(rest[start..end].to_string());
+        rest = &rest[end..];
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config_errors_carry_details() {
+        assert!(build_connector(&TlsConfig::default()).unwrap().is_none());
+
+        let missing = TlsConfig { ca_bundle_path: Some("/nonexistent/ca.pem".to_string()), ..Default::default() };
+        match build_connector(&missing) {
+            Err(TransportError::TlsError(detail)) => assert!(detail.contains("/nonexistent/ca.pem"), "{}", detail),
+            other => panic!("unexpected result: {:?}", other.map(|c| c.is_some())),
+        }
+
+        let half_identity = TlsConfig { client_cert_path: Some("cert.pem".to_string()), ..Default::default() };
+        assert!(matches!(build_connector(&half_identity), Err(TransportError::TlsError(_))));
+
+        let bundle = "junk\n-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n";
+        let blocks = split_pem_certificates(bundle);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[1].contains("BBBB"));
+    }
+}
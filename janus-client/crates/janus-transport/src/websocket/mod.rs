@@ -1,146 +1,309 @@
-use crate::connection::{ConnectParams, Transport};
-use janus_core::error::TransportError; // Use the core error type
+use crate::connection::{ConnectParams, Keepalive, Transport};
+use crate::discovery::DiscoveryClient;
+use crate::proxy::connect_via_proxy;
+use crate::tls;
+use janus_common::error::TransportError; // Shared with janus-core
 use async_trait::async_trait;
-use futures_util::{SinkExt, StreamExt, TryStreamExt};
+use futures_util::sink::Sink;
+use futures_util::stream::Stream;
+use futures_util::{SinkExt, StreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval_at, sleep_until, timeout, Instant, MissedTickBehavior};
+use tokio_util::sync::PollSender;
 use tokio_tungstenite::{
-    connect_async_with_config, MaybeTlsStream, WebSocketStream,
+    client_async_tls_with_config, connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream,
+    tungstenite::client::IntoClientRequest,
+    tungstenite::handshake::client::Request,
+    tungstenite::http::{HeaderName, HeaderValue},
     tungstenite::protocol::Message as WsMessage,
     tungstenite::protocol::WebSocketConfig,
     tungstenite::error::Error as WsError,
 };
-use tokio::time::timeout;
 use url::Url;
 
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
-#[derive(Debug)] // WebSocketStream doesn't impl Clone easily
+/// How long `disconnect` waits for the close handshake before dropping the socket.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Frames handed to the socket task but not yet written. Once the socket stops
+/// accepting writes this fills up and the sink stops being ready, so backpressure
+/// reaches `ConnectionActor`'s outbound queue.
+const OUTGOING_BUFFER: usize = 8;
+
+/// Read half of a WebSocket connection.
+///
+/// The socket itself is owned by a background task, which forwards text frames here,
+/// answers pings, and, when `ConnectParams::keepalive` is set, sends its own pings.
+/// A peer that stays silent past the pong deadline ends the stream with a
+/// `TransportError::Timeout`, which `ConnectionActor` handles as a lost connection.
+#[derive(Debug)]
 pub struct WebSocketTransport {
-    // Inner stream is wrapped in Option to allow taking ownership on disconnect
-    stream: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    incoming: mpsc::UnboundedReceiver<Result<String, TransportError>>,
+}
+
+/// Write half of a `WebSocketTransport`; queues text frames for the socket task.
+/// Only ready while the task keeps up with writing them to the socket.
+#[derive(Debug)]
+pub struct WebSocketSink {
+    outgoing: PollSender<String>,
+    task: JoinHandle<()>,
     url: Url, // Store parsed URL
 }
 
 #[async_trait]
 impl Transport for WebSocketTransport {
-    async fn connect(params: ConnectParams) -> Result<Self, TransportError> {
+    type Sink = WebSocketSink;
+
+    async fn connect(params: ConnectParams) -> Result<(Self, Self::Sink), TransportError> {
         log::debug!("Connecting WebSocket to: {}", params.url);
 
-        let url = Url::parse(&params.url)
+        // `http://host:9222` is resolved on every connect, since the browser id changes across restarts
+        let ws_url = if params.url.starts_with("http://") {
+            DiscoveryClient::new(&params.url, params.connect_timeout)?
+                .with_proxy(params.proxy.clone())
+                .browser_ws_url()
+                .await?
+        } else {
+            params.url.clone()
+        };
+
+        let url = Url::parse(&ws_url)
             .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
 
         let ws_config: Option<WebSocketConfig> = params.ws_config; // From ConnectParams
+        // Only wss:// needs a connector; `None` keeps tokio-tungstenite's default one
+        let connector = if url.scheme() == "wss" {
+            tls::build_connector(&params.tls)?.map(Connector::NativeTls)
+        } else {
+            None
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+        let request = build_request(&url, &params)?;
 
-        let connect_future = connect_async_with_config(url.clone(), ws_config, false); // `false` = disable_nagle
+        let connect_future = async {
+            match &params.proxy {
+                Some(proxy) => {
+                    // Dial through the proxy, then run the (TLS and) WebSocket handshake over the tunnel
+                    let host = url.host_str()
+                        .ok_or_else(|| TransportError::InvalidUrl(format!("No host in '{}'", url)))?;
+                    let port = url.port_or_known_default()
+                        .ok_or_else(|| TransportError::InvalidUrl(format!("No port in '{}'", url)))?;
+                    let tunnel = connect_via_proxy(proxy, host, port).await?;
+                    client_async_tls_with_config(request, tunnel, ws_config, connector).await
+                }
+                None => connect_async_tls_with_config(request, ws_config, false, connector).await, // `false` = disable_nagle
+            }
+            .map_err(|e| match map_ws_error(e) {
+                TransportError::TlsError(detail) => TransportError::TlsError(format!("TLS handshake with {} failed: {}", host, detail)),
+                other => other,
+            })
+        };
 
-        match timeout(params.connect_timeout, connect_future).await {
+        let stream = match timeout(params.connect_timeout, connect_future).await {
             Ok(Ok((stream, response))) => {
-                log::info!("WebSocket connected successfully to {}. Response status: {}", params.url, response.status());
+                log::info!("WebSocket connected successfully to {}. Response status: {}", url, response.status());
                 // Optional: Log response headers if needed (response.headers())
-                Ok(WebSocketTransport { stream: Some(stream), url })
+                stream
             }
             Ok(Err(e)) => {
                 log::error!("WebSocket connection error to {}: {}", params.url, e);
-                Err(map_ws_error(e))
+                return Err(e);
             }
             Err(_) => {
                 log::error!("WebSocket connection timed out after {:?} to {}", params.connect_timeout, params.url);
-                Err(TransportError::Timeout(format!(
+                return Err(TransportError::Timeout(format!(
                     "Connection timed out after {:?}",
                     params.connect_timeout
-                )))
+                )));
             }
-        }
+        };
+
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(OUTGOING_BUFFER);
+        let task = tokio::spawn(run_socket(stream, incoming_tx, outgoing_rx, params.keepalive, url.clone()));
+
+        Ok((
+            WebSocketTransport { incoming: incoming_rx },
+            WebSocketSink { outgoing: PollSender::new(outgoing_tx), task, url },
+        ))
     }
 
-    async fn disconnect(&mut self) -> Result<(), TransportError> {
-        log::debug!("Disconnecting WebSocket from: {}", self.url);
-        if let Some(mut stream) = self.stream.take() { // Take ownership
-            match stream.close(None).await {
-                Ok(_) => {
-                     log::info!("WebSocket closed gracefully for {}", self.url);
-                    Ok(())
-                },
-                Err(e) => {
-                    log::warn!("Error during WebSocket close for {}: {}", self.url, e);
-                    // Still consider it disconnected, but report potential issue
-                    Err(map_ws_error(e))
-                }
+    async fn disconnect(mut sink: Self::Sink) -> Result<(), TransportError> {
+        log::debug!("Disconnecting WebSocket from: {}", sink.url);
+        // Closing the sender tells the socket task to send a Close frame and exit
+        sink.outgoing.close();
+        match timeout(CLOSE_TIMEOUT, &mut sink.task).await {
+            Ok(_) => {
+                log::info!("WebSocket closed gracefully for {}", sink.url);
+                Ok(())
+            }
+            Err(_) => {
+                log::warn!("WebSocket close for {} did not finish within {:?}, dropping socket", sink.url, CLOSE_TIMEOUT);
+                sink.task.abort();
+                Err(TransportError::Timeout(format!("Close handshake timed out after {:?}", CLOSE_TIMEOUT)))
             }
-        } else {
-            // Already disconnected or never connected
-             log::debug!("WebSocket already disconnected for {}", self.url);
-            Ok(())
         }
     }
+}
 
-    async fn send(&mut self, message: String) -> Result<(), TransportError> {
-         log::trace!("Sending WebSocket message: {}", message); // Use trace for potentially verbose logs
-        if let Some(stream) = self.stream.as_mut() {
-            match stream.send(WsMessage::Text(message)).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    log::error!("WebSocket send error: {}", e);
-                    Err(map_ws_error(e))
-                }
-            }
-        } else {
-            Err(TransportError::NotConnected)
-        }
+impl Stream for WebSocketTransport {
+    type Item = Result<String, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx)
     }
+}
+
+impl Sink<String> for WebSocketSink {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Pending while the socket task's buffer is full
+        self.outgoing.poll_reserve(cx).map_err(|_| TransportError::NotConnected)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: String) -> Result<(), Self::Error> {
+        log::trace!("Sending WebSocket message: {}", item); // Use trace for potentially verbose logs
+        self.outgoing
+            .send_item(item)
+            .map_err(|_| TransportError::ConnectionClosed { reason: Some("WebSocket task has exited".to_string()) })
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Frames are flushed by the socket task as soon as it picks them up
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outgoing.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Builds the upgrade request: query-string tokens are added to the URL and extra
+/// headers to the request. Neither is logged.
+fn build_request(url: &Url, params: &ConnectParams) -> Result<Request, TransportError> {
+    let mut request_url = url.clone();
+    if !params.query_params.is_empty() {
+        // Sorted so the request is stable regardless of map order
+        let mut pairs: Vec<_> = params.query_params.iter().collect();
+        pairs.sort();
+        request_url.query_pairs_mut().extend_pairs(pairs);
+    }
+
+    let mut request = request_url.as_str().into_client_request().map_err(map_ws_error)?;
+    for (name, value) in &params.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| TransportError::InvalidUrl(format!("Invalid handshake header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| TransportError::InvalidUrl(format!("Invalid value for handshake header '{}': {}", name, e)))?;
+        request.headers_mut().insert(name, value);
+    }
+    Ok(request)
+}
+
+/// Owns the socket: forwards incoming text frames, writes queued outgoing ones and
+/// runs the keepalive. Returning drops `incoming`, which ends the read stream.
+async fn run_socket(
+    mut stream: WsStream,
+    incoming: mpsc::UnboundedSender<Result<String, TransportError>>,
+    mut outgoing: mpsc::Receiver<String>,
+    keepalive: Option<Keepalive>,
+    url: Url,
+) {
+    // With keepalive off the timer branch is disabled, so its period does not matter
+    let period = keepalive.map_or(Duration::from_secs(3600), |k| k.ping_interval);
+    let mut ping_timer = interval_at(Instant::now() + period, period);
+    ping_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // Set while a ping is outstanding
+    let mut pong_deadline: Option<Instant> = None;
 
-    async fn receive(&mut self) -> Option<Result<String, TransportError>> {
-        if let Some(stream) = self.stream.as_mut() {
-             match stream.next().await {
-                Some(Ok(msg)) => {
-                    log::trace!("Received WebSocket message: {:?}", msg);
-                    match msg {
-                        WsMessage::Text(text) => Some(Ok(text)),
-                        WsMessage::Binary(bin) => {
-                            // Decide how to handle binary data - error for now?
-                            log::warn!("Received unexpected binary WebSocket message ({} bytes)", bin.len());
-                            Some(Err(TransportError::ReceiveFailed("Received unexpected binary message".to_string())))
-                        },
-                        WsMessage::Ping(data) => {
-                            // Tungstenite handles pongs automatically by default
-                            log::trace!("Received WebSocket Ping: {:?}", data);
-                            // Need to recurse or loop to get the *next* actual message
-                            // This simple implementation might miss messages if ping/pong flood
-                            self.receive().await // Tail recursion might be okay here
-                        },
-                        WsMessage::Pong(data) => {
-                            log::trace!("Received WebSocket Pong: {:?}", data);
-                            // Recurse or loop
-                            self.receive().await
-                        },
-                        WsMessage::Close(close_frame) => {
-                            log::info!("Received WebSocket Close frame: {:?}", close_frame);
-                            None // Signal stream closure
-                        },
-                        WsMessage::Frame(_) => {
-                            // Raw frame, likely shouldn't happen with default config
-                            log::warn!("Received unexpected raw WebSocket frame");
-                            Some(Err(TransportError::ReceiveFailed("Received unexpected raw frame".to_string())))
+    loop {
+        tokio::select! {
+            frame = stream.next() => {
+                // Any frame proves the peer is alive; a pong may be queued behind large messages
+                pong_deadline = None;
+                match frame {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if incoming.send(Ok(text)).is_err() {
+                            log::debug!("WebSocket reader for {} dropped, closing socket task", url);
+                            break;
                         }
                     }
-                },
-                Some(Err(e)) => {
-                    log::error!("WebSocket receive error: {}", e);
-                     // Check if it's a "ConnectionClosed" error vs other IO error
-                     if matches!(e, WsError::ConnectionClosed | WsError::AlreadyClosed) {
-                         None // Treat as clean closure
-                     } else {
-                         Some(Err(map_ws_error(e))) // Report other errors
-                     }
-                },
-                None => {
-                     // Stream ended without a Close frame (unexpected EOF)
-                     log::warn!("WebSocket stream ended unexpectedly (EOF)");
-                    None // Signal stream closure
-                },
+                    Some(Ok(WsMessage::Binary(bin))) => {
+                        // Decide how to handle binary data - error for now?
+                        log::warn!("Received unexpected binary WebSocket message ({} bytes)", bin.len());
+                        let _ = incoming.send(Err(TransportError::ReceiveFailed("Received unexpected binary message".to_string())));
+                    }
+                    Some(Ok(WsMessage::Ping(data))) => {
+                        // Tungstenite queues the Pong reply and flushes it on the next read or write
+                        log::trace!("Received WebSocket Ping: {:?}", data);
+                    }
+                    Some(Ok(WsMessage::Pong(data))) => {
+                        log::trace!("Received WebSocket Pong: {:?}", data);
+                    }
+                    Some(Ok(WsMessage::Close(close_frame))) => {
+                        log::info!("Received WebSocket Close frame: {:?}", close_frame);
+                        break; // Signal stream closure
+                    }
+                    Some(Ok(WsMessage::Frame(_))) => {
+                        // Raw frame, likely shouldn't happen with default config
+                        log::warn!("Received unexpected raw WebSocket frame");
+                        let _ = incoming.send(Err(TransportError::ReceiveFailed("Received unexpected raw frame".to_string())));
+                    }
+                    Some(Err(e)) => {
+                        // Check if it's a "ConnectionClosed" error vs other IO error
+                        if !matches!(e, WsError::ConnectionClosed | WsError::AlreadyClosed) {
+                            log::error!("WebSocket receive error: {}", e);
+                            let _ = incoming.send(Err(map_ws_error(e)));
+                        }
+                        break;
+                    }
+                    None => {
+                        // Stream ended without a Close frame (unexpected EOF)
+                        log::warn!("WebSocket stream ended unexpectedly (EOF)");
+                        break;
+                    }
+                }
+            }
+            message = outgoing.recv() => {
+                let Some(text) = message else {
+                    // Sink dropped or closed: start the close handshake
+                    if let Err(e) = stream.close(None).await {
+                        log::warn!("Error during WebSocket close for {}: {}", url, e);
+                    }
+                    break;
+                };
+                if let Err(e) = stream.send(WsMessage::Text(text)).await {
+                    log::error!("WebSocket send error: {}", e);
+                    let _ = incoming.send(Err(map_ws_error(e)));
+                    break;
+                }
+            }
+            _ = ping_timer.tick(), if keepalive.is_some() && pong_deadline.is_none() => {
+                log::trace!("Sending keepalive Ping to {}", url);
+                if let Err(e) = stream.send(WsMessage::Ping(Vec::new())).await {
+                    log::error!("WebSocket keepalive ping error: {}", e);
+                    let _ = incoming.send(Err(map_ws_error(e)));
+                    break;
+                }
+                pong_deadline = keepalive.map(|k| Instant::now() + k.pong_timeout);
+            }
+            _ = sleep_until(pong_deadline.unwrap_or_else(Instant::now)), if pong_deadline.is_some() => {
+                let waited = keepalive.map(|k| k.pong_timeout).unwrap_or_default();
+                log::error!("WebSocket peer {} did not answer a keepalive ping within {:?}", url, waited);
+                let _ = incoming.send(Err(TransportError::Timeout(format!(
+                    "No response to keepalive ping within {:?}",
+                    waited
+                ))));
+                break;
             }
-        } else {
-            // Not connected, stream is None
-            None
         }
     }
 }
@@ -155,12 +318,138 @@ fn map_ws_error(e: WsError) -> TransportError {
         WsError::Tls(tls_err) => TransportError::TlsError(tls_err.to_string()),
         WsError::Capacity(cap_err) => TransportError::SendFailed(format!("Capacity error: {}", cap_err)), // Or specific capacity error type
         WsError::Protocol(proto_err) => TransportError::WebSocket(proto_err.to_string()),
-        WsError::SendQueueFull(_) => TransportError::SendFailed("Send queue full".to_string()),
+        WsError::WriteBufferFull(_) => TransportError::SendFailed("Write buffer full".to_string()),
         WsError::Utf8 => TransportError::Serde("Invalid UTF-8 received".to_string()),
         WsError::Url(url_err) => TransportError::InvalidUrl(url_err.to_string()),
+        WsError::Http(response) if matches!(response.status().as_u16(), 401 | 403) => {
+            // Hosted services usually explain the rejection in the body
+            let body = response.body().as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+            let detail = match body.trim() {
+                "" => response.status().canonical_reason().unwrap_or("rejected").to_string(),
+                text => text.chars().take(200).collect(),
+            };
+            TransportError::AuthenticationFailed { status: response.status().as_u16(), detail }
+        }
         WsError::Http(http_err) => TransportError::ConnectionFailed(format!("HTTP error during handshake: {}", http_err.status())),
         WsError::HttpFormat(http_fmt_err) => TransportError::ConnectionFailed(format!("HTTP format error: {}", http_fmt_err)),
         // Add other specific mappings as needed
          _ => TransportError::WebSocket(e.to_string()), // Catch-all for other WsError variants
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_keepalive_times_out_silent_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            // Complete the handshake, then never read again so pings go unanswered
+            let ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            drop(ws);
+        });
+
+        let params = ConnectParams {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            keepalive: Some(Keepalive {
+                ping_interval: Duration::from_millis(50),
+                pong_timeout: Duration::from_millis(100),
+            }),
+            ..ConnectParams::new(format!("ws://127.0.0.1:{}/devtools/browser/test", port))
+        };
+        let (mut reader, _sink) = WebSocketTransport::connect(params).await.unwrap();
+
+        let item = timeout(Duration::from_secs(2), reader.next()).await.expect("keepalive never fired");
+        assert!(matches!(item, Some(Err(TransportError::Timeout(_)))), "unexpected item: {:?}", item);
+        assert!(reader.next().await.is_none());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_sink_applies_backpressure_when_peer_stops_reading() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            // Complete the handshake, then never read again so the socket's buffers fill up
+            let ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            drop(ws);
+        });
+
+        let params = ConnectParams {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            ..ConnectParams::new(format!("ws://127.0.0.1:{}/devtools/browser/test", port))
+        };
+        let (_reader, mut sink) = WebSocketTransport::connect(params).await.unwrap();
+
+        // Loopback socket buffers hold a few MiB at most; an unbounded sink would accept all 256 MiB
+        let frame = "x".repeat(1024 * 1024);
+        let mut accepted = 0;
+        let stalled = loop {
+            match timeout(Duration::from_millis(500), sink.send(frame.clone())).await {
+                Ok(result) => result.unwrap(),
+                Err(_) => break true,
+            }
+            accepted += 1;
+            if accepted == 256 {
+                break false;
+            }
+        };
+        assert!(stalled, "sink accepted {} MiB without the peer reading", accepted);
+        assert!(accepted > OUTGOING_BUFFER, "sink stalled after only {} frames", accepted);
+        server.abort();
+    }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)] // The callback signature is tungstenite's
+    async fn test_handshake_headers_and_auth_rejection() {
+        use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request as ServerRequest, Response};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let _ = tokio_tungstenite::accept_hdr_async(socket, |request: &ServerRequest, response: Response| {
+                        let authorized = request.headers().get("authorization").map(|v| v.as_bytes()) == Some(b"Bearer s3cret")
+                            && request.uri().query() == Some("token=abc");
+                        if authorized {
+                            return Ok(response);
+                        }
+                        let mut rejection = ErrorResponse::new(Some("bad token".to_string()));
+                        *rejection.status_mut() = tokio_tungstenite::tungstenite::http::StatusCode::UNAUTHORIZED;
+                        Err(rejection)
+                    })
+                    .await;
+                });
+            }
+        });
+
+        let params = |authorized: bool| ConnectParams {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            headers: if authorized { [("Authorization".to_string(), "Bearer s3cret".to_string())].into() } else { Default::default() },
+            query_params: [("token".to_string(), "abc".to_string())].into(),
+            ..ConnectParams::new(format!("ws://127.0.0.1:{}/devtools/browser/test", port))
+        };
+
+        assert!(WebSocketTransport::connect(params(true)).await.is_ok());
+        match WebSocketTransport::connect(params(false)).await {
+            Err(TransportError::AuthenticationFailed { status, detail }) => {
+                assert_eq!(status, 401);
+                assert_eq!(detail, "bad token");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
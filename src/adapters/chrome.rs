@@ -2,11 +2,31 @@ use async_trait::async_trait;
 use serde_json::{json, Value};
 use crate::error::DebuggerError;
 use super::{ProtocolAdapter, Message, Connection};
+use janus_transport::recorder::{Direction, TrafficRecorder};
 use tokio_tungstenite::{connect_async, WebSocketStream, MaybeTlsStream};
 use tokio::net::TcpStream;
 use futures_util::{SinkExt, StreamExt};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::Stream;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsSink = SplitSink<WsStream, tokio_tungstenite::tungstenite::Message>;
+type PendingMap = HashMap<i64, oneshot::Sender<Message>>;
+type SubscriberList = Arc<Mutex<Vec<Subscriber>>>;
+
+/// Source of the connection ids written to traffic recordings
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Default time to wait for the response to a command
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Chrome DevTools Protocol adapter
 #[derive(Clone)]
@@ -27,24 +47,55 @@ impl ChromeAdapter {
         *id += 1;
         current
     }
-}
 
-#[async_trait]
-impl ProtocolAdapter for ChromeAdapter {
-    fn convert_command(&self, method: &str, params: Option<Value>) -> Result<String, DebuggerError> {
-        let command = json!({
-            "id": self.next_command_id(),
+    /// Serialize a command frame with an explicit id, scoped to a flattened
+    /// session when `session_id` is given
+    fn encode_command(&self, id: i64, method: &str, params: Option<Value>, session_id: Option<&str>) -> Result<String, DebuggerError> {
+        let mut command = json!({
+            "id": id,
             "method": method,
             "params": params.unwrap_or(json!({}))
         });
-        
+        if let Some(session_id) = session_id {
+            command["sessionId"] = json!(session_id);
+        }
+
         serde_json::to_string(&command)
-            .map_err(|e| DebuggerError::SerializationError(e))
+            .map_err(DebuggerError::SerializationError)
+    }
+
+    /// Parse an incoming text frame into a response or an event
+    fn decode_message(&self, text: &str) -> Result<Message, DebuggerError> {
+        let value: Value = serde_json::from_str(text)
+            .map_err(DebuggerError::SerializationError)?;
+
+        if let Some(id) = value.get("id").and_then(Value::as_i64) {
+            Ok(Message::Response {
+                id,
+                result: value.get("result").cloned(),
+                error: value.get("error").cloned(),
+            })
+        } else if let Some(method) = value.get("method").and_then(Value::as_str) {
+            Ok(Message::Event {
+                method: method.to_string(),
+                params: value.get("params").cloned().unwrap_or(json!({})),
+                session_id: value.get("sessionId").and_then(Value::as_str).map(|s| s.to_string()),
+            })
+        } else {
+            Err(DebuggerError::ProtocolError("Invalid message format".to_string()))
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolAdapter for ChromeAdapter {
+    fn convert_command(&self, method: &str, params: Option<Value>) -> Result<String, DebuggerError> {
+        self.encode_command(self.next_command_id(), method, params, None)
     }
 
     fn parse_response(&self, response: &str) -> Result<Value, DebuggerError> {
         let value: Value = serde_json::from_str(response)
-            .map_err(|e| DebuggerError::SerializationError(e))?;
+            .map_err(DebuggerError::SerializationError)?;
             
         if let Some(error) = value.get("error") {
             return Err(DebuggerError::ProtocolError(error.to_string()));
@@ -57,7 +108,7 @@ impl ProtocolAdapter for ChromeAdapter {
 
     fn convert_event(&self, event: &str) -> Result<(String, Value), DebuggerError> {
         let value: Value = serde_json::from_str(event)
-            .map_err(|e| DebuggerError::SerializationError(e))?;
+            .map_err(DebuggerError::SerializationError)?;
             
         let method = value.get("method")
             .and_then(Value::as_str)
@@ -71,23 +122,255 @@ impl ProtocolAdapter for ChromeAdapter {
     }
 }
 
+/// Selects which events an `EventStream` receives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventFilter {
+    /// Every event
+    All,
+    /// Events of a single method, e.g. `Network.requestWillBeSent`
+    Method(String),
+    /// Every event of a domain, e.g. `Network`
+    Domain(String),
+}
+
+impl EventFilter {
+    pub fn matches(&self, method: &str) -> bool {
+        match self {
+            EventFilter::All => true,
+            EventFilter::Method(m) => m == method,
+            EventFilter::Domain(d) => method
+                .strip_prefix(d.as_str())
+                .is_some_and(|rest| rest.starts_with('.')),
+        }
+    }
+}
+
+impl From<&str> for EventFilter {
+    /// `"*"` matches everything, `"Network.*"` matches a domain and anything
+    /// else is an exact method name
+    fn from(pattern: &str) -> Self {
+        if pattern == "*" {
+            EventFilter::All
+        } else if let Some(domain) = pattern.strip_suffix(".*") {
+            EventFilter::Domain(domain.to_string())
+        } else {
+            EventFilter::Method(pattern.to_string())
+        }
+    }
+}
+
+struct Subscriber {
+    filter: EventFilter,
+    session_id: Option<String>,
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+/// Stream of `Message::Event`s returned by `ChromeConnection::subscribe`
+///
+/// Ends when the connection closes. Dropping it unsubscribes.
+pub struct EventStream {
+    rx: mpsc::UnboundedReceiver<Message>,
+}
+
+impl Stream for EventStream {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Dispatcher shared by all clones of a connected `ChromeConnection`.
+///
+/// Owns the write half of the socket and a background task that reads every
+/// frame, hands responses to the caller waiting on the matching command id and
+/// fans events out to every matching subscriber.
+struct Dispatcher {
+    id: u64,
+    writer: tokio::sync::Mutex<WsSink>,
+    pending: Arc<Mutex<PendingMap>>,
+    reader: JoinHandle<()>,
+    recorder: Option<TrafficRecorder>,
+}
+
+impl Dispatcher {
+    fn start(stream: WsStream, adapter: ChromeAdapter, subscribers: SubscriberList, recorder: Option<TrafficRecorder>) -> Self {
+        let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+        let (write, read) = stream.split();
+        let pending = Arc::new(Mutex::new(PendingMap::new()));
+
+        let reader = tokio::spawn(Self::read_loop(id, read, adapter, pending.clone(), subscribers, recorder.clone()));
+
+        Self {
+            id,
+            writer: tokio::sync::Mutex::new(write),
+            pending,
+            reader,
+            recorder,
+        }
+    }
+
+    async fn read_loop(
+        id: u64,
+        mut read: SplitStream<WsStream>,
+        adapter: ChromeAdapter,
+        pending: Arc<Mutex<PendingMap>>,
+        subscribers: SubscriberList,
+        recorder: Option<TrafficRecorder>,
+    ) {
+        while let Some(frame) = read.next().await {
+            let text = match frame {
+                Ok(tokio_tungstenite::tungstenite::Message::Text(text)) => text,
+                Ok(tokio_tungstenite::tungstenite::Message::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    log::error!("Chrome connection read error: {}", e);
+                    break;
+                }
+            };
+            if let Some(recorder) = &recorder {
+                recorder.record(id, Direction::Inbound, &text);
+            }
+
+            match adapter.decode_message(&text) {
+                Ok(Message::Response { id, result, error }) => {
+                    match pending.lock().unwrap().remove(&id) {
+                        Some(tx) => {
+                            let _ = tx.send(Message::Response { id, result, error });
+                        }
+                        None => log::warn!("Dropping response for unknown command id {}", id),
+                    }
+                }
+                Ok(Message::Event { method, params, session_id }) => {
+                    let event = Message::Event { method: method.clone(), params, session_id: session_id.clone() };
+                    // Closed receivers are pruned as we go
+                    subscribers.lock().unwrap().retain(|sub| {
+                        let wanted = sub.session_id == session_id && sub.filter.matches(&method);
+                        !wanted || sub.tx.send(event.clone()).is_ok()
+                    });
+                }
+                Ok(Message::Command { .. }) => log::warn!("Ignoring command frame sent by the browser"),
+                Err(e) => log::warn!("Ignoring malformed frame: {}", e),
+            }
+        }
+
+        // Dropping the senders wakes every waiting caller with a closed channel
+        // and ends every event stream
+        pending.lock().unwrap().clear();
+        subscribers.lock().unwrap().clear();
+        log::debug!("Chrome connection reader finished");
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
 /// Chrome WebSocket connection
+///
+/// Clones share the same underlying socket. Commands are correlated with their
+/// responses by id, so any number of callers may have commands in flight.
+///
+/// A connection returned by `with_session` is scoped to one flattened CDP
+/// session: its commands carry the `sessionId` and its subscriptions only see
+/// that session's events.
 #[derive(Clone)]
 pub struct ChromeConnection {
-    ws_stream: Arc<Mutex<Option<(
-        futures_util::stream::SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, tokio_tungstenite::tungstenite::Message>,
-        futures_util::stream::SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>
-    )>>>,
+    dispatcher: Arc<Mutex<Option<Arc<Dispatcher>>>>,
+    session_id: Option<String>,
+    subscribers: SubscriberList,
+    // Backs `receive_message`; created on first use so unread events do not pile up
+    catch_all: Arc<tokio::sync::Mutex<Option<EventStream>>>,
     adapter: ChromeAdapter,
+    request_timeout: Duration,
+    recorder: Option<TrafficRecorder>,
 }
 
 impl ChromeConnection {
     pub fn new() -> Self {
         Self {
-            ws_stream: Arc::new(Mutex::new(None)),
+            dispatcher: Arc::new(Mutex::new(None)),
+            session_id: None,
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            catch_all: Arc::new(tokio::sync::Mutex::new(None)),
             adapter: ChromeAdapter::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            recorder: None,
+        }
+    }
+
+    /// A handle on the same socket scoped to a flattened session
+    pub fn with_session(&self, session_id: impl Into<String>) -> Self {
+        Self {
+            session_id: Some(session_id.into()),
+            catch_all: Arc::new(tokio::sync::Mutex::new(None)),
+            ..self.clone()
         }
     }
+
+    /// The flattened session this handle is scoped to, if any
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Attach to a target with `flatten: true` and return a handle scoped to
+    /// the new session
+    pub async fn attach_to_target(&self, target_id: &str) -> Result<Self, DebuggerError> {
+        let response = self.send_message(Message::Command {
+            id: 0,
+            method: "Target.attachToTarget".to_string(),
+            params: Some(json!({ "targetId": target_id, "flatten": true })),
+        }).await?;
+
+        match response {
+            Message::Response { result: Some(result), .. } => result.get("sessionId")
+                .and_then(Value::as_str)
+                .map(|session_id| self.with_session(session_id))
+                .ok_or_else(|| DebuggerError::ProtocolError("No sessionId in attachToTarget response".to_string())),
+            _ => Err(DebuggerError::ProtocolError("Invalid response type".to_string())),
+        }
+    }
+
+    /// Subscribe to events matching `filter`
+    ///
+    /// Accepts an `EventFilter` or a pattern such as `"Page.loadEventFired"` or
+    /// `"Network.*"`. Every subscriber whose filter matches receives its own copy
+    /// of the event. On a session-scoped handle only that session's events are
+    /// delivered, otherwise only browser-level ones. The stream ends when the
+    /// connection closes.
+    pub fn subscribe(&self, filter: impl Into<EventFilter>) -> Result<EventStream, DebuggerError> {
+        if !self.is_connected() {
+            return Err(DebuggerError::NotConnected);
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(Subscriber {
+            filter: filter.into(),
+            session_id: self.session_id.clone(),
+            tx,
+        });
+        Ok(EventStream { rx })
+    }
+
+    /// Set how long `send_message` waits for a response before giving up
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Record every frame of the next `connect` to `recorder`
+    pub fn with_recorder(mut self, recorder: TrafficRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn dispatcher(&self) -> Result<Arc<Dispatcher>, DebuggerError> {
+        self.dispatcher.lock().unwrap()
+            .clone()
+            .ok_or(DebuggerError::NotConnected)
+    }
 }
 
 #[async_trait]
@@ -98,76 +381,198 @@ impl Connection for ChromeConnection {
             
         let (ws_stream, _) = connect_async(&url).await
             .map_err(|e| DebuggerError::ConnectionError(e.to_string()))?;
-            
-        let (write, read) = ws_stream.split();
-        *self.ws_stream.lock().unwrap() = Some((write, read));
+
+        let dispatcher = Dispatcher::start(ws_stream, self.adapter.clone(), self.subscribers.clone(), self.recorder.clone());
+        *self.dispatcher.lock().unwrap() = Some(Arc::new(dispatcher));
         Ok(())
     }
 
     async fn disconnect(&mut self) -> Result<(), DebuggerError> {
-        if let Some((mut write, _)) = self.ws_stream.lock().unwrap().take() {
-            write.close().await
+        // A session handle only detaches its session, the socket stays open
+        if let Some(session_id) = self.session_id.clone() {
+            let browser = Self { session_id: None, ..self.clone() };
+            browser.send_message(Message::Command {
+                id: 0,
+                method: "Target.detachFromTarget".to_string(),
+                params: Some(json!({ "sessionId": session_id })),
+            }).await?;
+            // Kept until the browser confirms, so a failed detach can be retried
+            self.session_id = None;
+            return Ok(());
+        }
+
+        let dispatcher = self.dispatcher.lock().unwrap().take();
+        if let Some(dispatcher) = dispatcher {
+            dispatcher.writer.lock().await.close().await
                 .map_err(|e| DebuggerError::ConnectionError(e.to_string()))?;
         }
         Ok(())
     }
 
-    async fn send_message(&self, message: Message) -> Result<(), DebuggerError> {
-        if let Some((write, _)) = &*self.ws_stream.lock().unwrap() {
-            let msg = match message {
-                Message::Command { method, params, .. } => {
-                    self.adapter.convert_command(&method, params)?
-                },
-                _ => return Err(DebuggerError::InvalidArgument("Only commands can be sent".to_string())),
-            };
-            
-            let mut write = write.clone();
-            write.send(tokio_tungstenite::tungstenite::Message::Text(msg)).await
-                .map_err(|e| DebuggerError::ConnectionError(e.to_string()))?;
-        } else {
-            return Err(DebuggerError::NotConnected);
+    async fn send_message(&self, message: Message) -> Result<Message, DebuggerError> {
+        let (method, params) = match message {
+            Message::Command { method, params, .. } => (method, params),
+            _ => return Err(DebuggerError::InvalidArgument("Only commands can be sent".to_string())),
+        };
+
+        let dispatcher = self.dispatcher()?;
+        let id = self.adapter.next_command_id();
+        let frame = self.adapter.encode_command(id, &method, params, self.session_id.as_deref())?;
+
+        let (tx, rx) = oneshot::channel();
+        dispatcher.pending.lock().unwrap().insert(id, tx);
+
+        if let Some(recorder) = &dispatcher.recorder {
+            recorder.record(dispatcher.id, Direction::Outbound, &frame);
         }
-        Ok(())
+        let sent = dispatcher.writer.lock().await
+            .send(tokio_tungstenite::tungstenite::Message::Text(frame)).await;
+        if let Err(e) = sent {
+            dispatcher.pending.lock().unwrap().remove(&id);
+            return Err(DebuggerError::ConnectionError(e.to_string()));
+        }
+
+        let response = match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(DebuggerError::ConnectionError("Connection closed".to_string())),
+            Err(_) => {
+                dispatcher.pending.lock().unwrap().remove(&id);
+                return Err(DebuggerError::TimeoutError(format!(
+                    "{} (id {}) got no response within {:?}", method, id, self.request_timeout
+                )));
+            }
+        };
+
+        if let Message::Response { error: Some(error), .. } = &response {
+            return Err(DebuggerError::ProtocolError(error.to_string()));
+        }
+        Ok(response)
     }
 
     async fn receive_message(&self) -> Result<Message, DebuggerError> {
-        if let Some((_, read)) = &*self.ws_stream.lock().unwrap() {
-            let mut read = read.clone();
-            
-            match read.next().await {
-                Some(Ok(msg)) => {
-                    match msg {
-                        tokio_tungstenite::tungstenite::Message::Text(text) => {
-                            let value: Value = serde_json::from_str(&text)
-                                .map_err(|e| DebuggerError::SerializationError(e))?;
-                                
-                            if value.get("id").is_some() {
-                                Ok(Message::Response {
-                                    id: value["id"].as_i64().unwrap(),
-                                    result: value.get("result").cloned(),
-                                    error: value.get("error").cloned(),
-                                })
-                            } else if value.get("method").is_some() {
-                                Ok(Message::Event {
-                                    method: value["method"].as_str().unwrap().to_string(),
-                                    params: value.get("params").cloned().unwrap_or(json!({})),
-                                })
-                            } else {
-                                Err(DebuggerError::ProtocolError("Invalid message format".to_string()))
-                            }
-                        },
-                        _ => Err(DebuggerError::ProtocolError("Unexpected message type".to_string())),
-                    }
-                },
-                Some(Err(e)) => Err(DebuggerError::ConnectionError(e.to_string())),
-                None => Err(DebuggerError::ConnectionError("Connection closed".to_string())),
+        let mut catch_all = self.catch_all.lock().await;
+        if catch_all.is_none() {
+            *catch_all = Some(self.subscribe(EventFilter::All)?);
+        }
+
+        match catch_all.as_mut().unwrap().next().await {
+            Some(event) => Ok(event),
+            None => {
+                *catch_all = None;
+                Err(DebuggerError::ConnectionError("Connection closed".to_string()))
             }
-        } else {
-            Err(DebuggerError::NotConnected)
         }
     }
 
     fn is_connected(&self) -> bool {
-        self.ws_stream.lock().unwrap().is_some()
+        self.dispatcher.lock().unwrap()
+            .as_ref()
+            .is_some_and(|d| !d.reader.is_finished())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock_cdp::{MockCdpServer, INITIAL_TARGET_ID};
+
+    fn command(method: &str, params: Value) -> Message {
+        Message::Command { id: 0, method: method.to_string(), params: Some(params) }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_event_filter_patterns() {
+        let domain = EventFilter::from("Network.*");
+        assert_eq!(domain, EventFilter::Domain("Network".to_string()));
+        assert!(domain.matches("Network.requestWillBeSent"));
+        assert!(!domain.matches("NetworkFoo.bar"));
+        assert!(!domain.matches("Page.loadEventFired"));
+
+        let method = EventFilter::from("Page.loadEventFired");
+        assert!(method.matches("Page.loadEventFired"));
+        assert!(!method.matches("Page.frameNavigated"));
+
+        assert!(EventFilter::from("*").matches("Target.targetCreated"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_commands_get_their_own_responses() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut browser = ChromeConnection::new().with_request_timeout(Duration::from_secs(5));
+        browser.connect(&server.ws_url()).await.unwrap();
+
+        let evaluations = (1..=5).map(|n| {
+            let browser = browser.clone();
+            async move {
+                let response = browser.send_message(command("Runtime.evaluate", json!({ "expression": n.to_string() }))).await;
+                (n, response)
+            }
+        });
+        for (n, response) in futures_util::future::join_all(evaluations).await {
+            match response.unwrap() {
+                Message::Response { result: Some(result), .. } => assert_eq!(result["result"]["value"], n),
+                other => panic!("unexpected response {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_command_times_out_without_blocking_others() {
+        let server = MockCdpServer::start().await.unwrap();
+        server.on("Slow.op", |_| mock_cdp::Reply::silence());
+        let mut browser = ChromeConnection::new().with_request_timeout(Duration::from_millis(200));
+        browser.connect(&server.ws_url()).await.unwrap();
+
+        let (slow, version) = tokio::join!(
+            browser.send_message(command("Slow.op", json!({}))),
+            browser.send_message(command("Browser.getVersion", json!({}))),
+        );
+        assert!(matches!(slow, Err(DebuggerError::TimeoutError(detail)) if detail.contains("Slow.op")));
+        assert!(matches!(version, Ok(Message::Response { result: Some(_), .. })));
+        // The timed-out command no longer waits for a response
+        assert!(browser.dispatcher().unwrap().pending.lock().unwrap().is_empty());
+        assert!(browser.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_session_commands_events_and_disconnect() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut browser = ChromeConnection::new().with_request_timeout(Duration::from_secs(5));
+        browser.connect(&server.ws_url()).await.unwrap();
+
+        let page = browser.attach_to_target(INITIAL_TARGET_ID).await.unwrap();
+        let mut loads = page.subscribe("Page.loadEventFired").unwrap();
+        page.send_message(command("Page.navigate", json!({ "url": "https://example.com/" }))).await.unwrap();
+        assert!(matches!(loads.next().await, Some(Message::Event { session_id: Some(_), .. })));
+
+        server.fail_next("Runtime.evaluate", -32000, "Execution context was destroyed.");
+        let failed = page.send_message(command("Runtime.evaluate", json!({ "expression": "1" }))).await;
+        assert!(matches!(failed, Err(DebuggerError::ProtocolError(detail)) if detail.contains("destroyed")));
+
+        server.disconnect_on("Browser.getVersion");
+        let dropped = browser.send_message(command("Browser.getVersion", json!({}))).await;
+        assert!(matches!(dropped, Err(DebuggerError::ConnectionError(_))));
+        assert!(loads.next().await.is_none());
+        assert!(!browser.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_failed_detach_keeps_the_session() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut browser = ChromeConnection::new().with_request_timeout(Duration::from_secs(5));
+        browser.connect(&server.ws_url()).await.unwrap();
+        let mut page = browser.attach_to_target(INITIAL_TARGET_ID).await.unwrap();
+        let session_id = page.session_id().unwrap().to_string();
+
+        server.fail_next("Target.detachFromTarget", -32000, "Detach refused");
+        assert!(matches!(page.disconnect().await, Err(DebuggerError::ProtocolError(_))));
+        assert_eq!(page.session_id(), Some(session_id.as_str()));
+
+        page.disconnect().await.unwrap();
+        assert_eq!(page.session_id(), None);
+        let detaches: Vec<_> = server.received().into_iter().filter(|r| r.method == "Target.detachFromTarget").collect();
+        assert_eq!(detaches.len(), 2);
+        assert!(detaches.iter().all(|r| r.params["sessionId"] == session_id.as_str()));
+        assert!(browser.is_connected());
+    }
+}
@@ -33,6 +33,8 @@ pub enum Message {
     Event {
         method: String,
         params: Value,
+        /// Flattened session the event belongs to, `None` for browser-level events
+        session_id: Option<String>,
     },
 }
 
@@ -45,10 +47,12 @@ pub trait Connection: Send + Sync {
     /// Disconnect from the endpoint
     async fn disconnect(&mut self) -> Result<(), DebuggerError>;
     
-    /// Send a message
-    async fn send_message(&self, message: Message) -> Result<(), DebuggerError>;
+    /// Send a command and wait for its matching response
+    ///
+    /// The id carried by the command is ignored; the connection assigns its own.
+    async fn send_message(&self, message: Message) -> Result<Message, DebuggerError>;
     
-    /// Receive a message
+    /// Receive the next event
     async fn receive_message(&self) -> Result<Message, DebuggerError>;
     
     /// Check if the connection is active
@@ -62,6 +62,18 @@ impl From<url::ParseError> for DebuggerError {
     }
 }
 
+impl From<janus_common::error::TransportError> for DebuggerError {
+    fn from(err: janus_common::error::TransportError) -> Self {
+        use janus_common::error::TransportError;
+        match err {
+            TransportError::Timeout(message) => DebuggerError::TimeoutError(message),
+            TransportError::InvalidUrl(_) | TransportError::UnsupportedScheme(_) => DebuggerError::InvalidArgument(err.to_string()),
+            TransportError::NotConnected => DebuggerError::NotConnected,
+            other => DebuggerError::ConnectionError(other.to_string()),
+        }
+    }
+}
+
 impl<T> From<SendError<T>> for DebuggerError {
     fn from(err: SendError<T>) -> Self {
         DebuggerError::NetworkError(err.to_string())
@@ -3,7 +3,7 @@ use serde_json::Value;
 use crate::core::{Dom, Element};
 use crate::error::DebuggerError;
 use crate::adapters::chrome::ChromeConnection;
-use crate::adapters::Message;
+use crate::adapters::{Connection, Message};
 
 #[derive(Clone)]
 pub struct ChromeDom {
@@ -28,7 +28,7 @@ impl Dom for ChromeDom {
         }).await?;
         
         if let Message::Response { result, .. } = response {
-            if let Some(node_id) = result.and_then(|v| v.get("nodeId")).and_then(|v| v.as_i64()) {
+            if let Some(node_id) = result.as_ref().and_then(|v| v.get("nodeId")).and_then(|v| v.as_i64()) {
                 // Get node details
                 let details = self.connection.send_message(Message::Command {
                     id: 2,
@@ -99,7 +99,7 @@ impl Dom for ChromeDom {
         }).await?;
         
         if let Message::Response { result, .. } = response {
-            if let Some(styles) = result.and_then(|v| v.get("inlineStyle")) {
+            if let Some(styles) = result.as_ref().and_then(|v| v.get("inlineStyle")) {
                 // Update the style text
                 self.connection.send_message(Message::Command {
                     id: 2,
@@ -4,14 +4,25 @@ mod network;
 
 use async_trait::async_trait;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
 use crate::core::{BrowserDebugger, Page};
 use crate::error::DebuggerError;
+use crate::adapters::Connection;
 use crate::adapters::chrome::{ChromeAdapter, ChromeConnection};
+use janus_transport::DiscoveryClient;
 use page::ChromePage;
 
+/// How long `connect` waits for `/json/version` when given an `http://` endpoint
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct ChromeDebugger {
     connection: ChromeConnection,
     adapter: ChromeAdapter,
+    // Flattened sessions attached to pages, keyed by target id. Pages and
+    // `execute_script` share them, so a target is attached to at most once
+    sessions: Mutex<HashMap<String, ChromeConnection>>,
 }
 
 impl ChromeDebugger {
@@ -19,6 +30,37 @@ impl ChromeDebugger {
         Self {
             connection: ChromeConnection::new(),
             adapter: ChromeAdapter::new(),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Session-scoped connection for a target, attaching on first use
+    async fn session(&self, target_id: &str) -> Result<ChromeConnection, DebuggerError> {
+        if let Some(session) = self.sessions.lock().unwrap().get(target_id) {
+            return Ok(session.clone());
+        }
+
+        let session = self.connection.attach_to_target(target_id).await?;
+        self.sessions.lock().unwrap().insert(target_id.to_string(), session.clone());
+        Ok(session)
+    }
+}
+
+impl Drop for ChromeDebugger {
+    fn drop(&mut self) {
+        // Pages handed out keep the socket open, so their sessions would outlive us
+        let sessions: Vec<ChromeConnection> = self.sessions.get_mut().unwrap().drain().map(|(_, session)| session).collect();
+        if sessions.is_empty() {
+            return;
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                for mut session in sessions {
+                    if let Err(e) = session.disconnect().await {
+                        log::debug!("Failed to detach session on drop: {}", e);
+                    }
+                }
+            });
         }
     }
 }
@@ -26,7 +68,13 @@ impl ChromeDebugger {
 #[async_trait]
 impl BrowserDebugger for ChromeDebugger {
     async fn connect(&mut self, endpoint: &str) -> Result<(), DebuggerError> {
-        self.connection.connect(endpoint).await?;
+        // `http://host:9222` is resolved to the browser WebSocket via `/json/version`
+        let ws_url = if endpoint.starts_with("http://") {
+            DiscoveryClient::new(endpoint, DISCOVERY_TIMEOUT)?.browser_ws_url().await?
+        } else {
+            endpoint.to_string()
+        };
+        self.connection.connect(&ws_url).await?;
         
         // Enable necessary domains
         self.connection.send_message(crate::adapters::Message::Command {
@@ -39,6 +87,8 @@ impl BrowserDebugger for ChromeDebugger {
     }
     
     async fn disconnect(&mut self) -> Result<(), DebuggerError> {
+        // Closing the socket ends every session with it
+        self.sessions.lock().unwrap().clear();
         self.connection.disconnect().await
     }
     
@@ -52,13 +102,14 @@ impl BrowserDebugger for ChromeDebugger {
         // Parse response and create ChromePage instances
         let mut pages = Vec::new();
         if let crate::adapters::Message::Response { result, .. } = response {
-            if let Some(targets) = result.and_then(|v| v.get("targetInfos")).and_then(|v| v.as_array()) {
+            if let Some(targets) = result.as_ref().and_then(|v| v.get("targetInfos")).and_then(|v| v.as_array()) {
                 for target in targets {
+                    if target.get("type").and_then(|v| v.as_str()) != Some("page") {
+                        continue;
+                    }
                     if let Some(target_id) = target.get("targetId").and_then(|v| v.as_str()) {
-                        pages.push(Box::new(ChromePage::new(
-                            target_id.to_string(),
-                            self.connection.clone(),
-                        )) as Box<dyn Page>);
+                        let session = self.session(target_id).await?;
+                        pages.push(Box::new(ChromePage::new(target_id.to_string(), session)) as Box<dyn Page>);
                     }
                 }
             }
@@ -68,12 +119,12 @@ impl BrowserDebugger for ChromeDebugger {
     }
     
     async fn execute_script(&self, page_id: &str, script: &str) -> Result<Value, DebuggerError> {
-        let response = self.connection.send_message(crate::adapters::Message::Command {
+        let session = self.session(page_id).await?;
+        let response = session.send_message(crate::adapters::Message::Command {
             id: 1,
             method: "Runtime.evaluate".to_string(),
             params: Some(serde_json::json!({
                 "expression": script,
-                "targetId": page_id,
             })),
         }).await?;
         
@@ -98,11 +149,9 @@ impl BrowserDebugger for ChromeDebugger {
         }).await?;
         
         if let crate::adapters::Message::Response { result, .. } = response {
-            if let Some(target_id) = result.and_then(|v| v.get("targetId")).and_then(|v| v.as_str()) {
-                Ok(Box::new(ChromePage::new(
-                    target_id.to_string(),
-                    self.connection.clone(),
-                )) as Box<dyn Page>)
+            if let Some(target_id) = result.as_ref().and_then(|v| v.get("targetId")).and_then(|v| v.as_str()) {
+                let session = self.session(target_id).await?;
+                Ok(Box::new(ChromePage::new(target_id.to_string(), session)) as Box<dyn Page>)
             } else {
                 Err(DebuggerError::ProtocolError("No target ID in response".to_string()))
             }
@@ -112,6 +161,14 @@ impl BrowserDebugger for ChromeDebugger {
     }
     
     async fn close_page(&mut self, page_id: &str) -> Result<(), DebuggerError> {
+        let session = self.sessions.lock().unwrap().remove(page_id);
+        if let Some(mut session) = session {
+            // Closing the target ends its session anyway
+            if let Err(e) = session.disconnect().await {
+                log::warn!("Failed to detach from {} before closing it: {}", page_id, e);
+            }
+        }
+
         self.connection.send_message(crate::adapters::Message::Command {
             id: 1,
             method: "Target.closeTarget".to_string(),
@@ -131,7 +188,7 @@ impl BrowserDebugger for ChromeDebugger {
         }).await?;
         
         if let crate::adapters::Message::Response { result, .. } = response {
-            result
+            result.as_ref()
                 .and_then(|v| v.get("product"))
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string())
@@ -140,4 +197,83 @@ impl BrowserDebugger for ChromeDebugger {
             Err(DebuggerError::ProtocolError("Invalid response type".to_string()))
         }
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock_cdp::{MockCdpServer, Reply, INITIAL_TARGET_ID};
+
+    #[tokio::test]
+    async fn test_debugger_against_mock_browser() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut debugger = ChromeDebugger::new();
+        debugger.connect(&server.ws_url()).await.unwrap();
+
+        assert_eq!(debugger.get_browser_version().await.unwrap(), "MockChrome/1.0");
+        assert_eq!(debugger.get_pages().await.unwrap().len(), 1);
+
+        let mut page = debugger.create_page(Some("https://example.com/")).await.unwrap();
+        page.navigate("https://example.org/").await.unwrap();
+
+        server.on("Runtime.evaluate", |_| Reply::result(serde_json::json!({ "result": { "type": "string", "value": "Example" } })));
+        let title = debugger.execute_script(page.get_id(), "document.title").await.unwrap();
+        assert_eq!(title["result"]["value"], "Example");
+
+        debugger.close_page(page.get_id()).await.unwrap();
+        assert_eq!(debugger.get_pages().await.unwrap().len(), 1);
+        debugger.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pages_reuse_one_session_per_target() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut debugger = ChromeDebugger::new();
+        debugger.connect(&server.ws_url()).await.unwrap();
+        let requests = |method: &str| server.received().into_iter().filter(|r| r.method == method).collect::<Vec<_>>();
+
+        debugger.get_pages().await.unwrap();
+        let pages = debugger.get_pages().await.unwrap();
+        debugger.execute_script(INITIAL_TARGET_ID, "1 + 1").await.unwrap();
+        assert_eq!(requests("Target.attachToTarget").len(), 1);
+
+        debugger.close_page(pages[0].get_id()).await.unwrap();
+        let detached = requests("Target.detachFromTarget");
+        assert_eq!(detached.len(), 1);
+        assert_eq!(detached[0].params["sessionId"], format!("SESSION-{}", INITIAL_TARGET_ID));
+        debugger.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_page_closes_the_target_even_if_detach_fails() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut debugger = ChromeDebugger::new();
+        debugger.connect(&server.ws_url()).await.unwrap();
+        debugger.execute_script(INITIAL_TARGET_ID, "1 + 1").await.unwrap();
+
+        server.fail_next("Target.detachFromTarget", -32000, "Detach refused");
+        debugger.close_page(INITIAL_TARGET_ID).await.unwrap();
+        let closed: Vec<_> = server.received().into_iter().filter(|r| r.method == "Target.closeTarget").collect();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].params["targetId"], INITIAL_TARGET_ID);
+        assert!(debugger.get_pages().await.unwrap().is_empty());
+        debugger.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropping_debugger_detaches_sessions() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut debugger = ChromeDebugger::new();
+        debugger.connect(&server.ws_url()).await.unwrap();
+
+        // The page keeps the socket open after the debugger is gone
+        let _page = debugger.create_page(None).await.unwrap();
+        drop(debugger);
+        for _ in 0..50 {
+            if server.received().iter().any(|r| r.method == "Target.detachFromTarget") {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("session was not detached");
+    }
+}
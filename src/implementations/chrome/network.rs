@@ -1,15 +1,41 @@
 use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde_json::Value;
 use crate::core::{Network, NetworkRequest};
 use crate::error::DebuggerError;
 use crate::adapters::chrome::ChromeConnection;
-use crate::adapters::Message;
+use crate::adapters::{Connection, Message};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use tokio::task::JoinHandle;
+
+type RequestMap = Arc<Mutex<HashMap<String, NetworkRequest>>>;
+
+/// The task folding `Network.*` events into the request map, aborted once the last
+/// clone of its `ChromeNetwork` is dropped
+#[derive(Default)]
+struct Listener(Mutex<Option<JoinHandle<()>>>);
+
+impl Listener {
+    fn replace(&self, task: Option<JoinHandle<()>>) {
+        let previous = std::mem::replace(&mut *self.0.lock().unwrap(), task);
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.replace(None);
+    }
+}
 
 #[derive(Clone)]
 pub struct ChromeNetwork {
     connection: ChromeConnection,
-    requests: Arc<Mutex<HashMap<String, NetworkRequest>>>,
+    requests: RequestMap,
+    listener: Arc<Listener>,
 }
 
 impl ChromeNetwork {
@@ -17,6 +43,42 @@ impl ChromeNetwork {
         Self {
             connection,
             requests: Arc::new(Mutex::new(HashMap::new())),
+            listener: Arc::new(Listener::default()),
+        }
+    }
+
+    /// Fold a single `Network.*` event into the request map
+    fn record_event(requests: &RequestMap, method: &str, params: &Value) {
+        let request_id = match params.get("requestId").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => return,
+        };
+        let mut requests = requests.lock().unwrap();
+
+        match method {
+            "Network.requestWillBeSent" => {
+                let request = params.get("request").unwrap_or(&Value::Null);
+                requests.insert(request_id.clone(), NetworkRequest {
+                    request_id,
+                    url: request.get("url").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    method: request.get("method").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    status: None,
+                    status_text: None,
+                });
+            }
+            "Network.responseReceived" => {
+                if let Some(entry) = requests.get_mut(&request_id) {
+                    let response = params.get("response").unwrap_or(&Value::Null);
+                    entry.status = response.get("status").and_then(|v| v.as_i64()).map(|s| s as i32);
+                    entry.status_text = response.get("statusText").and_then(|v| v.as_str()).map(|s| s.to_string());
+                }
+            }
+            "Network.loadingFailed" => {
+                if let Some(entry) = requests.get_mut(&request_id) {
+                    entry.status_text = params.get("errorText").and_then(|v| v.as_str()).map(|s| s.to_string());
+                }
+            }
+            _ => {}
         }
     }
 }
@@ -24,18 +86,31 @@ impl ChromeNetwork {
 #[async_trait]
 impl Network for ChromeNetwork {
     async fn enable(&mut self) -> Result<(), DebuggerError> {
+        // Subscribe first so no event between enable and listen is lost
+        let mut events = self.connection.subscribe("Network.*")?;
+
         self.connection.send_message(Message::Command {
             id: 1,
             method: "Network.enable".to_string(),
             params: None,
         }).await?;
         
-        // Set up event listeners for network events
-        // TODO: Implement proper event handling
+        let requests = self.requests.clone();
+        let listener = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                if let Message::Event { method, params, .. } = event {
+                    Self::record_event(&requests, &method, &params);
+                }
+            }
+        });
+
+        self.listener.replace(Some(listener));
         Ok(())
     }
     
     async fn disable(&mut self) -> Result<(), DebuggerError> {
+        self.listener.replace(None);
+
         self.connection.send_message(Message::Command {
             id: 1,
             method: "Network.disable".to_string(),
@@ -52,10 +127,9 @@ impl Network for ChromeNetwork {
     }
     
     async fn clear(&mut self) -> Result<(), DebuggerError> {
-        let mut requests = self.requests.lock()
-            .map_err(|_| DebuggerError::Unknown("Failed to lock requests".to_string()))?;
-            
-        requests.clear();
+        self.requests.lock()
+            .map_err(|_| DebuggerError::Unknown("Failed to lock requests".to_string()))?
+            .clear();
         
         self.connection.send_message(Message::Command {
             id: 1,
@@ -65,4 +139,80 @@ impl Network for ChromeNetwork {
         
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock_cdp::MockCdpServer;
+    use serde_json::json;
+    use std::time::Duration;
+
+    async fn connected(server: &MockCdpServer) -> ChromeConnection {
+        let mut connection = ChromeConnection::new().with_request_timeout(Duration::from_secs(5));
+        connection.connect(&server.ws_url()).await.unwrap();
+        connection
+    }
+
+    /// Emits the events of one successful request
+    async fn load(server: &MockCdpServer, request_id: &str, url: &str) {
+        server.emit("Network.requestWillBeSent", json!({ "requestId": request_id, "request": { "url": url, "method": "GET" } }), None);
+        server.emit("Network.responseReceived", json!({ "requestId": request_id, "response": { "status": 200, "statusText": "OK" } }), None);
+        server.emit("Network.loadingFinished", json!({ "requestId": request_id, "encodedDataLength": 512 }), None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    #[tokio::test]
+    async fn test_network_events_populate_requests() {
+        let server = MockCdpServer::start().await.unwrap();
+        let mut network = ChromeNetwork::new(connected(&server).await);
+        network.enable().await.unwrap();
+
+        load(&server, "R1", "https://example.com/").await;
+        let requests = network.get_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request_id, "R1");
+        assert_eq!(requests[0].url, "https://example.com/");
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].status, Some(200));
+        assert_eq!(requests[0].status_text.as_deref(), Some("OK"));
+        assert!(server.received().iter().any(|r| r.method == "Network.enable"));
+
+        network.disable().await.unwrap();
+        load(&server, "R2", "https://example.com/late").await;
+        assert_eq!(network.get_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_each_see_network_events() {
+        let server = MockCdpServer::start().await.unwrap();
+        let connection = connected(&server).await;
+        let mut first = ChromeNetwork::new(connection.clone());
+        let mut second = ChromeNetwork::new(connection.clone());
+        let mut raw = connection.subscribe("Network.requestWillBeSent").unwrap();
+        first.enable().await.unwrap();
+        second.enable().await.unwrap();
+
+        load(&server, "R1", "https://example.com/").await;
+        assert_eq!(first.get_requests().await.unwrap()[0].status, Some(200));
+        assert_eq!(second.get_requests().await.unwrap()[0].status, Some(200));
+        match raw.next().await {
+            Some(Message::Event { method, params, .. }) => {
+                assert_eq!(method, "Network.requestWillBeSent");
+                assert_eq!(params["requestId"], "R1");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // The listener stops with the last clone, not the first
+        let requests = first.requests.clone();
+        let clone = first.clone();
+        drop(first);
+        load(&server, "R2", "https://example.com/2").await;
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        drop(clone);
+        load(&server, "R3", "https://example.com/3").await;
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(second.get_requests().await.unwrap().len(), 3);
+    }
+}
@@ -3,7 +3,7 @@ use serde_json::Value;
 use crate::core::{Page, Dom, Network};
 use crate::error::DebuggerError;
 use crate::adapters::chrome::ChromeConnection;
-use crate::adapters::Message;
+use crate::adapters::{Connection, Message};
 use super::dom::ChromeDom;
 use super::network::ChromeNetwork;
 
@@ -17,6 +17,7 @@ pub struct ChromePage {
 }
 
 impl ChromePage {
+    /// Build a page on a connection already scoped to the target's session
     pub fn new(id: String, connection: ChromeConnection) -> Self {
         Self {
             id,
@@ -49,13 +50,12 @@ impl Page for ChromePage {
             method: "Page.navigate".to_string(),
             params: Some(serde_json::json!({
                 "url": url,
-                "targetId": self.id,
             })),
         }).await?;
         
-        if let Message::Response { result, error } = response {
-            if error.is_some() {
-                return Err(DebuggerError::PageError("Navigation failed".to_string()));
+        if let Message::Response { result, .. } = response {
+            if let Some(error_text) = result.as_ref().and_then(|v| v.get("errorText")).and_then(|v| v.as_str()) {
+                return Err(DebuggerError::PageError(format!("Navigation failed: {}", error_text)));
             }
             self.url = url.to_string();
             
@@ -74,7 +74,6 @@ impl Page for ChromePage {
             method: "Page.reload".to_string(),
             params: Some(serde_json::json!({
                 "ignoreCache": ignore_cache,
-                "targetId": self.id,
             })),
         }).await?;
         
@@ -98,12 +97,11 @@ impl Page for ChromePage {
             method: "Page.captureScreenshot".to_string(),
             params: Some(serde_json::json!({
                 "format": format,
-                "targetId": self.id,
             })),
         }).await?;
         
         if let Message::Response { result, .. } = response {
-            if let Some(data) = result.and_then(|v| v.get("data")).and_then(|v| v.as_str()) {
+            if let Some(data) = result.as_ref().and_then(|v| v.get("data")).and_then(|v| v.as_str()) {
                 base64::decode(data)
                     .map_err(|e| DebuggerError::ProtocolError(format!("Invalid base64 data: {}", e)))
             } else {